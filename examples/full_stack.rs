@@ -1,7 +1,6 @@
-use std::vec;
+use std::{io, vec};
 
 use flagship::recipes::*;
-use itertools::Itertools;
 
 fn main() -> anyhow::Result<()> {
     let stack = Stack::builder(
@@ -33,10 +32,6 @@ fn main() -> anyhow::Result<()> {
     )))
     .build();
 
-    let values = stack.as_k8s()?;
-    let yaml: Vec<String> = values.iter().map(serde_yaml::to_string).try_collect()?;
-    let yaml = yaml.join("\n---\n");
-
-    println!("{yaml}");
+    stack.write_yaml(io::stdout().lock())?;
     Ok(())
 }
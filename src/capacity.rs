@@ -0,0 +1,528 @@
+use std::{collections::BTreeMap, fmt, ops::Add};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use thiserror::Error;
+
+use crate::{
+    check::{sequence, str_at, ObjectRef},
+    helm::short_name,
+    k8s::{
+        quantity::{Quantity, QuantityError},
+        validation::{field, index},
+    },
+    lint::pod_spec,
+};
+
+const GIB: f64 = 1073741824.0;
+const HOURS_PER_MONTH: f64 = 730.0;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CapacityError {
+    #[error("QuantityError: {object}: {path}: {source}")]
+    QuantityError {
+        object: ObjectRef,
+        path: String,
+        source: QuantityError,
+    },
+}
+
+pub type CapacityResult<T> = Result<T, CapacityError>;
+
+/// Resources reserved by a set of pods and claims.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resources {
+    cpu_requests: Quantity,
+    cpu_limits: Quantity,
+    memory_requests: Quantity,
+    memory_limits: Quantity,
+    storage: Quantity,
+    pods: u64,
+    claims: u64,
+}
+
+/// What one workload or claim reserves, per replica and in total.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentEstimate {
+    object: ObjectRef,
+    name: String,
+    replicas: u64,
+    per_replica: Resources,
+    total: Resources,
+}
+
+/// Reserved resources of rendered objects, per component and per namespace.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Estimate {
+    components: Vec<ComponentEstimate>,
+}
+
+/// A quota limit the estimate doesn't fit in.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaExceeded {
+    resource: String,
+    estimated: Quantity,
+    hard: Quantity,
+}
+
+/// Prices to turn reservations into a monthly cost, in any currency. Reads from YAML or TOML
+/// with camelCase keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceTable {
+    cpu_core_hour: f64,
+    memory_gib_hour: f64,
+    storage_gib_month: f64,
+}
+
+impl Resources {
+    pub fn cpu_requests(&self) -> Quantity {
+        self.cpu_requests
+    }
+
+    pub fn cpu_limits(&self) -> Quantity {
+        self.cpu_limits
+    }
+
+    pub fn memory_requests(&self) -> Quantity {
+        self.memory_requests
+    }
+
+    pub fn memory_limits(&self) -> Quantity {
+        self.memory_limits
+    }
+
+    pub fn storage(&self) -> Quantity {
+        self.storage
+    }
+
+    pub fn pods(&self) -> u64 {
+        self.pods
+    }
+
+    pub fn claims(&self) -> u64 {
+        self.claims
+    }
+
+    fn scale(self, replicas: u64) -> Self {
+        let factor = replicas as f64;
+        Resources {
+            cpu_requests: self.cpu_requests * factor,
+            cpu_limits: self.cpu_limits * factor,
+            memory_requests: self.memory_requests * factor,
+            memory_limits: self.memory_limits * factor,
+            storage: self.storage * factor,
+            pods: self.pods * replicas,
+            claims: self.claims * replicas,
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        let max = |a: Quantity, b: Quantity| if b > a { b } else { a };
+        Resources {
+            cpu_requests: max(self.cpu_requests, other.cpu_requests),
+            cpu_limits: max(self.cpu_limits, other.cpu_limits),
+            memory_requests: max(self.memory_requests, other.memory_requests),
+            memory_limits: max(self.memory_limits, other.memory_limits),
+            storage: max(self.storage, other.storage),
+            pods: self.pods.max(other.pods),
+            claims: self.claims.max(other.claims),
+        }
+    }
+
+    /// The value counted against a ResourceQuota `hard` key, if the key is one we estimate.
+    fn quota_usage(&self, key: &str) -> Option<Quantity> {
+        Some(match key {
+            "cpu" | "requests.cpu" => self.cpu_requests,
+            "limits.cpu" => self.cpu_limits,
+            "memory" | "requests.memory" => self.memory_requests,
+            "limits.memory" => self.memory_limits,
+            "requests.storage" => self.storage,
+            "pods" => Quantity::new(self.pods as f64),
+            "persistentvolumeclaims" => Quantity::new(self.claims as f64),
+            _ => return None,
+        })
+    }
+}
+
+impl Add for Resources {
+    type Output = Resources;
+
+    fn add(self, other: Resources) -> Resources {
+        Resources {
+            cpu_requests: self.cpu_requests + other.cpu_requests,
+            cpu_limits: self.cpu_limits + other.cpu_limits,
+            memory_requests: self.memory_requests + other.memory_requests,
+            memory_limits: self.memory_limits + other.memory_limits,
+            storage: self.storage + other.storage,
+            pods: self.pods + other.pods,
+            claims: self.claims + other.claims,
+        }
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cpu {}/{}, memory {}/{}, storage {}, {} pods, {} claims",
+            self.cpu_requests.to_cores(),
+            self.cpu_limits.to_cores(),
+            self.memory_requests.to_bytes(),
+            self.memory_limits.to_bytes(),
+            self.storage.to_bytes(),
+            self.pods,
+            self.claims
+        )
+    }
+}
+
+impl ComponentEstimate {
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    /// The object name without its namespace prefix.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn replicas(&self) -> u64 {
+        self.replicas
+    }
+
+    pub fn per_replica(&self) -> &Resources {
+        &self.per_replica
+    }
+
+    pub fn total(&self) -> &Resources {
+        &self.total
+    }
+}
+
+impl Estimate {
+    /// Estimates what `objects` reserve. Pods reserve the sum of their containers or, when
+    /// higher, their largest init container; StatefulSet claims are counted per replica.
+    pub fn from_objects(objects: &[Value]) -> CapacityResult<Self> {
+        let mut components = Vec::default();
+        for object in objects {
+            let per_replica = match (str_at(&object["kind"]), pod_spec(object)) {
+                (_, Some((pod_path, pod))) => {
+                    let mut resources = pod_resources(object, pod_path, pod)?;
+                    for (i, claim) in sequence(&object["spec"]["volumeClaimTemplates"])
+                        .iter()
+                        .enumerate()
+                    {
+                        let path = format!(
+                            "{}.spec.resources.requests.storage",
+                            index("spec.volumeClaimTemplates", i)
+                        );
+                        resources.storage = resources.storage
+                            + quantity(
+                                object,
+                                &path,
+                                &claim["spec"]["resources"]["requests"]["storage"],
+                            )?;
+                        resources.claims += 1;
+                    }
+                    resources
+                }
+                ("PersistentVolumeClaim", None) => Resources {
+                    storage: quantity(
+                        object,
+                        "spec.resources.requests.storage",
+                        &object["spec"]["resources"]["requests"]["storage"],
+                    )?,
+                    claims: 1,
+                    ..Resources::default()
+                },
+                _ => continue,
+            };
+
+            let replicas = replicas(object);
+            let object = ObjectRef::of(object);
+            components.push(ComponentEstimate {
+                name: short_name(object.name(), object.namespace()),
+                object,
+                replicas,
+                per_replica,
+                total: per_replica.scale(replicas),
+            });
+        }
+        Ok(Estimate { components })
+    }
+
+    pub fn components(&self) -> &[ComponentEstimate] {
+        &self.components
+    }
+
+    pub fn total(&self) -> Resources {
+        self.components
+            .iter()
+            .fold(Resources::default(), |total, c| total + c.total)
+    }
+
+    /// Totals per namespace, which is one per environment for recipe stacks.
+    pub fn by_namespace(&self) -> BTreeMap<&str, Resources> {
+        let mut totals: BTreeMap<&str, Resources> = BTreeMap::default();
+        for component in &self.components {
+            let total = totals.entry(component.object.namespace()).or_default();
+            *total = *total + component.total;
+        }
+        totals
+    }
+
+    /// Compares the estimate against the `hard` limits of a ResourceQuota, using the totals of
+    /// the quota's namespace or, for a quota without one, of everything.
+    pub fn check_quota(&self, quota: &Value) -> CapacityResult<Vec<QuotaExceeded>> {
+        let namespace = str_at(&quota["metadata"]["namespace"]);
+        let used = match namespace {
+            "" => self.total(),
+            namespace => self
+                .by_namespace()
+                .get(namespace)
+                .copied()
+                .unwrap_or_default(),
+        };
+
+        let mut exceeded = Vec::default();
+        for (key, hard) in quota["spec"]["hard"].as_mapping().into_iter().flatten() {
+            let key = str_at(key);
+            let Some(estimated) = used.quota_usage(key) else {
+                continue;
+            };
+            let hard = quantity(quota, &field("spec.hard", key), hard)?;
+            if estimated > hard {
+                exceeded.push(QuotaExceeded {
+                    resource: key.into(),
+                    estimated,
+                    hard,
+                });
+            }
+        }
+        Ok(exceeded)
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for component in &self.components {
+            writeln!(
+                f,
+                "{} x{}: {}",
+                component.object, component.replicas, component.total
+            )?;
+        }
+        for (namespace, total) in self.by_namespace() {
+            writeln!(f, "namespace {namespace}: {total}")?;
+        }
+        write!(f, "total: {}", self.total())
+    }
+}
+
+impl QuotaExceeded {
+    pub fn resource(&self) -> &str {
+        &self.resource
+    }
+
+    pub fn estimated(&self) -> Quantity {
+        self.estimated
+    }
+
+    pub fn hard(&self) -> Quantity {
+        self.hard
+    }
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: estimated {} exceeds quota {}",
+            self.resource, self.estimated, self.hard
+        )
+    }
+}
+
+impl PriceTable {
+    pub fn new(cpu_core_hour: f64, memory_gib_hour: f64, storage_gib_month: f64) -> Self {
+        PriceTable {
+            cpu_core_hour,
+            memory_gib_hour,
+            storage_gib_month,
+        }
+    }
+
+    /// Monthly cost of reserving `resources`, priced on requests.
+    pub fn monthly(&self, resources: &Resources) -> f64 {
+        let compute = resources.cpu_requests.value() * self.cpu_core_hour
+            + resources.memory_requests.value() / GIB * self.memory_gib_hour;
+        compute * HOURS_PER_MONTH + resources.storage.value() / GIB * self.storage_gib_month
+    }
+}
+
+fn replicas(object: &Value) -> u64 {
+    let spec = &object["spec"];
+    let replicas = match str_at(&object["kind"]) {
+        "Job" => &spec["parallelism"],
+        "CronJob" => &spec["jobTemplate"]["spec"]["parallelism"],
+        "Deployment" | "StatefulSet" | "ReplicaSet" => &spec["replicas"],
+        _ => &Value::Null,
+    };
+    replicas.as_u64().unwrap_or(1)
+}
+
+fn pod_resources(object: &Value, pod_path: &str, pod: &Value) -> CapacityResult<Resources> {
+    let mut running = Resources {
+        pods: 1,
+        ..Resources::default()
+    };
+    let mut init_peak = Resources::default();
+
+    // Sidecars (init containers that keep running) reserve alongside the containers and
+    // during later init containers.
+    let init_path = field(pod_path, "initContainers");
+    for (i, container) in sequence(&pod["initContainers"]).iter().enumerate() {
+        let resources = container_resources(object, &index(&init_path, i), container)?;
+        if container["restartPolicy"] == "Always" {
+            running = running + resources;
+        } else {
+            init_peak = init_peak.max(running + resources);
+        }
+    }
+    let containers_path = field(pod_path, "containers");
+    for (i, container) in sequence(&pod["containers"]).iter().enumerate() {
+        running = running + container_resources(object, &index(&containers_path, i), container)?;
+    }
+    Ok(running.max(init_peak))
+}
+
+/// The requests and limits of a container. Like Kubernetes, an unset request defaults to the
+/// limit.
+fn container_resources(object: &Value, path: &str, container: &Value) -> CapacityResult<Resources> {
+    let resources = &container["resources"];
+    let path = field(path, "resources");
+    let get = |kind: &str, name: &str, default: Quantity| {
+        let value = &resources[kind][name];
+        match value {
+            Value::Null => Ok(default),
+            _ => quantity(object, &field(&field(&path, kind), name), value),
+        }
+    };
+    let cpu_limits = get("limits", "cpu", Quantity::default())?;
+    let memory_limits = get("limits", "memory", Quantity::default())?;
+    Ok(Resources {
+        cpu_requests: get("requests", "cpu", cpu_limits)?,
+        cpu_limits,
+        memory_requests: get("requests", "memory", memory_limits)?,
+        memory_limits,
+        ..Resources::default()
+    })
+}
+
+fn quantity(object: &Value, path: &str, value: &Value) -> CapacityResult<Quantity> {
+    Quantity::from_value(value).map_err(|source| CapacityError::QuantityError {
+        object: ObjectRef::of(object),
+        path: path.into(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::*;
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Ephemeral("pr-1".into()))
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 3)))
+            .build();
+        let estimate = stack.estimate()?;
+
+        let total = estimate.total();
+        assert_eq!(total.pods(), 4);
+        assert_eq!(total.claims(), 1);
+        assert_eq!(total.storage().to_bytes(), "5Gi");
+        assert_eq!(
+            estimate.by_namespace().keys().collect::<Vec<_>>(),
+            vec![&"example-pr-1"]
+        );
+
+        let quota: Value = serde_yaml::from_str(
+            "{kind: ResourceQuota, metadata: {namespace: example-pr-1}, spec: {hard: {pods: 3, requests.storage: 10Gi}}}",
+        )?;
+        let exceeded: Vec<String> = estimate
+            .check_quota(&quota)?
+            .iter()
+            .map(QuotaExceeded::to_string)
+            .collect();
+        assert_eq!(exceeded, vec!["pods: estimated 4 exceeds quota 3"]);
+
+        let prices = PriceTable::new(0.04, 0.005, 0.1);
+        assert_eq!(prices.monthly(&total), 0.5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn init_containers_count_by_peak() -> anyhow::Result<()> {
+        let objects: Vec<Value> = serde_yaml::from_str(
+            r#"
+            - apiVersion: apps/v1
+              kind: Deployment
+              metadata: {name: app-web, namespace: app}
+              spec:
+                replicas: 2
+                template:
+                  spec:
+                    initContainers:
+                      - name: migrate
+                        resources: {requests: {cpu: "1", memory: 64Mi}}
+                      - name: proxy
+                        restartPolicy: Always
+                        resources: {requests: {cpu: 100m, memory: 32Mi}}
+                    containers:
+                      - name: web
+                        resources:
+                          requests: {cpu: 250m, memory: 256Mi}
+                          limits: {cpu: 500m, memory: 512Mi}
+            - apiVersion: v1
+              kind: PersistentVolumeClaim
+              metadata: {name: app-data, namespace: app}
+              spec: {resources: {requests: {storage: 1Gi}}}
+            "#,
+        )?;
+        let estimate = Estimate::from_objects(&objects)?;
+
+        let web = &estimate.components()[0];
+        assert_eq!(web.name(), "web");
+        assert_eq!(web.per_replica().cpu_requests().to_cores(), "1");
+        assert_eq!(web.per_replica().memory_requests().to_bytes(), "288Mi");
+        assert_eq!(web.total().cpu_limits().to_cores(), "1");
+        assert_eq!(
+            estimate.total().to_string(),
+            "cpu 2/1, memory 576Mi/1Gi, storage 1Gi, 2 pods, 1 claims"
+        );
+
+        let mut limited = objects[0].clone();
+        limited["spec"]["template"]["spec"]["containers"][0]["resources"] =
+            serde_yaml::from_str("{requests: {cpu: 250m}, limits: {cpu: 500m, memory: 512Mi}}")?;
+        let limited = Estimate::from_objects(&[limited])?;
+        let web = limited.components()[0].per_replica();
+        assert_eq!(web.cpu_requests().to_cores(), "1");
+        assert_eq!(web.memory_requests().to_bytes(), "544Mi");
+
+        let mut broken = objects[1].clone();
+        broken["spec"]["resources"]["requests"]["storage"] = "lots".into();
+        assert_eq!(
+            Estimate::from_objects(&[broken]).unwrap_err().to_string(),
+            "QuantityError: PersistentVolumeClaim app/app-data: spec.resources.requests.storage: invalid quantity \"lots\""
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::k8s::validation::{field, index};
+
+const POD_SPEC: &str = "spec.template.spec";
+
+/// Identifies a rendered object by kind, namespace and name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectRef {
+    kind: String,
+    namespace: String,
+    name: String,
+}
+
+/// A reference from one object to another, or within an object, that doesn't resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReference {
+    object: ObjectRef,
+    path: String,
+    message: String,
+}
+
+impl ObjectRef {
+    pub(crate) fn new(kind: &str, namespace: &str, name: &str) -> Self {
+        ObjectRef {
+            kind: kind.into(),
+            namespace: namespace.into(),
+            name: name.into(),
+        }
+    }
+
+    pub(crate) fn of(object: &Value) -> Self {
+        ObjectRef {
+            kind: str_at(&object["kind"]).into(),
+            namespace: str_at(&object["metadata"]["namespace"]).into(),
+            name: str_at(&object["metadata"]["name"]).into(),
+        }
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.namespace.is_empty() {
+            write!(f, "{} {}", self.kind, self.name)
+        } else {
+            write!(f, "{} {}/{}", self.kind, self.namespace, self.name)
+        }
+    }
+}
+
+impl DanglingReference {
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for DanglingReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.object, self.path, self.message)
+    }
+}
+
+/// Checks that the objects of a stack agree with each other: workload selectors match their
+/// pod template, volume mounts refer to a volume or claim template, Service target ports are
+/// exposed by a selected container and StatefulSets are governed by a Service in the set.
+///
+/// Objects are compared within their namespace. Kinds flagship doesn't model are ignored.
+pub fn check(objects: &[Value]) -> Vec<DanglingReference> {
+    let mut checker = Checker {
+        objects,
+        found: Vec::default(),
+    };
+
+    for object in objects {
+        match str_at(&object["kind"]) {
+            "Deployment" => checker.workload(object),
+            "StatefulSet" => {
+                checker.workload(object);
+                checker.governing_service(object);
+            }
+            "Service" => checker.service(object),
+            _ => {}
+        }
+    }
+
+    checker.found
+}
+
+struct Checker<'a> {
+    objects: &'a [Value],
+    found: Vec<DanglingReference>,
+}
+
+impl Checker<'_> {
+    fn push<P: Into<String>, M: Into<String>>(&mut self, object: &Value, path: P, message: M) {
+        self.found.push(DanglingReference {
+            object: ObjectRef::of(object),
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn workload(&mut self, object: &Value) {
+        let labels = &object["spec"]["template"]["metadata"]["labels"];
+        if let Value::Mapping(selector) = &object["spec"]["selector"]["matchLabels"] {
+            for (key, value) in selector {
+                if labels.get(key) != Some(value) {
+                    self.push(
+                        object,
+                        field("spec.selector.matchLabels", str_at(key)),
+                        format!(
+                            "selector {}={} does not match the pod template labels",
+                            str_at(key),
+                            str_at(value)
+                        ),
+                    );
+                }
+            }
+        }
+
+        let pod = &object["spec"]["template"]["spec"];
+        let mut volumes: Vec<&str> = sequence(&pod["volumes"])
+            .iter()
+            .map(|volume| str_at(&volume["name"]))
+            .collect();
+        volumes.extend(
+            sequence(&object["spec"]["volumeClaimTemplates"])
+                .iter()
+                .map(|claim| str_at(&claim["metadata"]["name"])),
+        );
+
+        for (path, container) in containers(POD_SPEC, pod) {
+            for (i, mount) in sequence(&container["volumeMounts"]).iter().enumerate() {
+                let name = str_at(&mount["name"]);
+                if !volumes.contains(&name) {
+                    self.push(
+                        object,
+                        field(&index(&field(&path, "volumeMounts"), i), "name"),
+                        format!("no volume or volume claim template named {name:?}"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn governing_service(&mut self, object: &Value) {
+        let service_name = str_at(&object["spec"]["serviceName"]);
+        if service_name.is_empty() {
+            return;
+        }
+
+        let namespace = &object["metadata"]["namespace"];
+        let exists = self.objects.iter().any(|other| {
+            other["kind"] == "Service"
+                && other["metadata"]["namespace"] == *namespace
+                && other["metadata"]["name"] == service_name
+        });
+        if !exists {
+            self.push(
+                object,
+                "spec.serviceName",
+                format!("no Service named {service_name:?}"),
+            );
+        }
+    }
+
+    fn service(&mut self, object: &Value) {
+        let selector = match &object["spec"]["selector"] {
+            Value::Mapping(selector) if !selector.is_empty() => selector,
+            _ => return,
+        };
+
+        let namespace = &object["metadata"]["namespace"];
+        let pods: Vec<&Value> = self
+            .objects
+            .iter()
+            .filter(|other| matches!(str_at(&other["kind"]), "Deployment" | "StatefulSet"))
+            .filter(|other| other["metadata"]["namespace"] == *namespace)
+            .filter(|other| selects(selector, &other["spec"]["template"]["metadata"]["labels"]))
+            .map(|other| &other["spec"]["template"]["spec"])
+            .collect();
+
+        if pods.is_empty() {
+            self.push(object, "spec.selector", "selects no pods in the stack");
+            return;
+        }
+
+        let ports = sequence(&object["spec"]["ports"]);
+        for (i, port) in ports.iter().enumerate() {
+            let target_port = match &port["targetPort"] {
+                Value::Null => &port["port"],
+                target_port => target_port,
+            };
+
+            let exposed = pods.iter().any(|pod| {
+                containers(POD_SPEC, pod).iter().any(|(_, container)| {
+                    sequence(&container["ports"])
+                        .iter()
+                        .any(|container_port| match target_port {
+                            Value::String(name) => container_port["name"] == name.as_str(),
+                            number => container_port["containerPort"] == *number,
+                        })
+                })
+            });
+
+            if !exposed {
+                let target = match target_port {
+                    Value::String(name) => name.clone(),
+                    number => number.as_u64().unwrap_or_default().to_string(),
+                };
+                self.push(
+                    object,
+                    field(&index("spec.ports", i), "targetPort"),
+                    format!("no selected container exposes port {target}"),
+                );
+            }
+        }
+    }
+}
+
+pub(crate) fn selects(selector: &Mapping, labels: &Value) -> bool {
+    selector
+        .iter()
+        .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+/// Every container of a pod spec with its path, init containers first.
+pub(crate) fn containers<'a>(pod_path: &str, pod: &'a Value) -> Vec<(String, &'a Value)> {
+    ["initContainers", "containers"]
+        .into_iter()
+        .flat_map(|kind| {
+            sequence(&pod[kind])
+                .iter()
+                .enumerate()
+                .map(move |(i, container)| (index(&field(pod_path, kind), i), container))
+        })
+        .collect()
+}
+
+pub(crate) fn sequence(value: &Value) -> &[Value] {
+    value.as_sequence().map(Vec::as_slice).unwrap_or_default()
+}
+
+pub(crate) fn str_at(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::recipes::*;
+
+    #[test]
+    fn recipes_are_consistent() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::RabbitMQ(RabbitMQ::new("rabbitmq:3".into())))
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+
+        assert_eq!(stack.check()?, Vec::default());
+        Ok(())
+    }
+
+    #[test]
+    fn reports_dangling_references() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: StatefulSet
+metadata:
+  name: db
+  namespace: example
+spec:
+  serviceName: db-headless
+  selector:
+    matchLabels:
+      app: db
+  template:
+    metadata:
+      labels:
+        app: database
+    spec:
+      containers:
+      - name: db
+        image: postgres:16
+        ports:
+        - containerPort: 5432
+          name: pg
+        volumeMounts:
+        - name: data
+          mountPath: /var/lib/postgresql/data
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: db
+  namespace: example
+spec:
+  selector:
+    app: database
+  ports:
+  - port: 5432
+    targetPort: pg
+  - port: 9187
+    targetPort: 9187
+"#;
+        let objects: Vec<Value> = serde_yaml::Deserializer::from_str(yaml)
+            .map(Value::deserialize)
+            .collect::<Result<_, _>>()?;
+
+        let found: Vec<String> = check(&objects).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            found,
+            vec![
+                "StatefulSet example/db: spec.selector.matchLabels.app: selector app=db does not match the pod template labels",
+                "StatefulSet example/db: spec.template.spec.containers[0].volumeMounts[0].name: no volume or volume claim template named \"data\"",
+                "StatefulSet example/db: spec.serviceName: no Service named \"db-headless\"",
+                "Service example/db: spec.ports[1].targetPort: no selected container exposes port 9187",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    check::{selects, sequence, str_at},
+    helm::short_name,
+    k8s::image::ImageRef,
+    lint::pod_spec,
+};
+
+/// A docker-compose project running the workloads of a stack on one machine.
+///
+/// Each container becomes a service named after its workload without the namespace prefix
+/// (`<workload>-<container>` when a pod has more than one), publishing the ports Services route
+/// to it and keeping StatefulSet claims in named volumes. A host port that is already published
+/// moves to the next free one, so services sharing a port can run side by side. Kubernetes
+/// Service DNS names in environment variables are rewritten to compose service names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Compose {
+    services: BTreeMap<String, ComposeService>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    volumes: BTreeMap<String, Mapping>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ComposeService {
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entrypoint: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    environment: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ports: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    volumes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    healthcheck: Option<Healthcheck>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Healthcheck {
+    test: Vec<String>,
+    interval: String,
+    timeout: String,
+    retries: u32,
+}
+
+/// Health checks for well-known images whose recipes don't declare probes.
+#[rustfmt::skip]
+const KNOWN_HEALTHCHECKS: &[(&str, &str)] = &[
+    ("postgres", "pg_isready -U postgres"),
+    ("rabbitmq", "rabbitmq-diagnostics -q ping"),
+    ("nginx", "curl -fsS http://localhost/ > /dev/null || exit 1"),
+];
+
+impl Compose {
+    pub fn services(&self) -> &BTreeMap<String, ComposeService> {
+        &self.services
+    }
+
+    pub fn volumes(&self) -> impl Iterator<Item = &str> {
+        self.volumes.keys().map(String::as_str)
+    }
+
+    pub fn to_yaml(&self) -> serde_yaml::Result<String> {
+        serde_yaml::to_string(self)
+    }
+
+    /// Builds a project from rendered objects. Objects other than workloads and the Services,
+    /// Secrets and ConfigMaps they use are ignored.
+    pub fn from_objects(objects: &[Value]) -> Self {
+        let mut compose = Compose::default();
+        let mut hosts = Vec::default();
+        let mut published = BTreeSet::default();
+
+        for object in objects {
+            let Some((_, pod)) = pod_spec(object) else {
+                continue;
+            };
+            let namespace = str_at(&object["metadata"]["namespace"]);
+            let workload = short_name(str_at(&object["metadata"]["name"]), namespace);
+            let containers = sequence(&pod["containers"]);
+            let claims: Vec<&str> = sequence(&object["spec"]["volumeClaimTemplates"])
+                .iter()
+                .map(|claim| str_at(&claim["metadata"]["name"]))
+                .collect();
+
+            for container in containers {
+                let name = match containers.len() {
+                    1 => workload.clone(),
+                    _ => format!(
+                        "{workload}-{}",
+                        short_name(str_at(&container["name"]), namespace)
+                    ),
+                };
+                let mut service = ComposeService {
+                    image: str_at(&container["image"]).into(),
+                    entrypoint: strings(&container["command"]),
+                    command: strings(&container["args"]),
+                    healthcheck: Healthcheck::of(container),
+                    ..ComposeService::default()
+                };
+
+                for var in sequence(&container["env"]) {
+                    if let Some(value) = env_value(var, namespace, objects) {
+                        service
+                            .environment
+                            .insert(str_at(&var["name"]).into(), value);
+                    }
+                }
+
+                for mount in sequence(&container["volumeMounts"]) {
+                    let claim = str_at(&mount["name"]);
+                    if claims.contains(&claim) {
+                        let volume = short_name(claim, namespace);
+                        let path = str_at(&mount["mountPath"]);
+                        service.volumes.push(format!("{volume}:{path}"));
+                        compose.volumes.insert(volume, Mapping::default());
+                    }
+                }
+
+                for target in objects.iter().filter(|o| o["kind"] == "Service") {
+                    let selector = target["spec"]["selector"].as_mapping();
+                    let labels = &object["spec"]["template"]["metadata"]["labels"];
+                    if str_at(&target["metadata"]["namespace"]) != namespace
+                        || !selector.is_some_and(|s| !s.is_empty() && selects(s, labels))
+                    {
+                        continue;
+                    }
+                    let mut routed = false;
+                    for port in sequence(&target["spec"]["ports"]) {
+                        if let Some(container_port) = container_port(container, port) {
+                            let mut host_port = port["port"].as_u64().unwrap_or(container_port);
+                            while !published.insert(host_port) {
+                                host_port += 1;
+                            }
+                            service.ports.push(format!("{host_port}:{container_port}"));
+                            routed = true;
+                        }
+                    }
+                    if routed {
+                        hosts.push((
+                            str_at(&target["metadata"]["name"]).to_string(),
+                            namespace.to_string(),
+                            name.clone(),
+                        ));
+                    }
+                }
+
+                compose.services.insert(name, service);
+            }
+        }
+
+        for service in compose.services.values_mut() {
+            for value in service.environment.values_mut() {
+                for (host, namespace, target) in &hosts {
+                    *value = rewrite_host(value, host, namespace, target);
+                }
+            }
+        }
+        compose
+    }
+}
+
+impl ComposeService {
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    pub fn environment(&self) -> &BTreeMap<String, String> {
+        &self.environment
+    }
+
+    pub fn ports(&self) -> &[String] {
+        &self.ports
+    }
+
+    pub fn volumes(&self) -> &[String] {
+        &self.volumes
+    }
+
+    pub fn healthcheck(&self) -> Option<&Healthcheck> {
+        self.healthcheck.as_ref()
+    }
+}
+
+impl Healthcheck {
+    fn new(test: Vec<String>) -> Self {
+        Healthcheck {
+            test,
+            interval: "10s".into(),
+            timeout: "5s".into(),
+            retries: 5,
+        }
+    }
+
+    /// Translates the readiness (or else liveness) probe, falling back to a known check for
+    /// the image.
+    fn of(container: &Value) -> Option<Self> {
+        let probe = ["readinessProbe", "livenessProbe"]
+            .into_iter()
+            .map(|probe| &container[probe])
+            .find(|probe| probe.is_mapping());
+
+        if let Some(probe) = probe {
+            if let Some(command) = strings(&probe["exec"]["command"]) {
+                return Some(Healthcheck::new([vec!["CMD".into()], command].concat()));
+            }
+            let http = &probe["httpGet"];
+            if http.is_mapping() {
+                let port = probe_port(container, &http["port"]);
+                let path = http["path"].as_str().unwrap_or("/");
+                return Some(Healthcheck::shell(format!(
+                    "curl -fsS http://localhost:{port}{path} > /dev/null || exit 1"
+                )));
+            }
+            let tcp = &probe["tcpSocket"];
+            if tcp.is_mapping() {
+                let port = probe_port(container, &tcp["port"]);
+                return Some(Healthcheck::shell(format!("nc -z localhost {port}")));
+            }
+        }
+
+        let image = ImageRef::parse(str_at(&container["image"])).ok()?;
+        let repository = image.repository().rsplit('/').next().unwrap_or_default();
+        KNOWN_HEALTHCHECKS
+            .iter()
+            .find(|(known, _)| *known == repository)
+            .map(|(_, command)| Healthcheck::shell(command.to_string()))
+    }
+
+    fn shell(command: String) -> Self {
+        Healthcheck::new(vec!["CMD-SHELL".into(), command])
+    }
+
+    pub fn test(&self) -> &[String] {
+        &self.test
+    }
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    let items = value.as_sequence()?;
+    Some(items.iter().map(|item| str_at(item).to_string()).collect())
+}
+
+/// The literal value of an environment variable, resolving Secret and ConfigMap references
+/// to objects of the stack.
+fn env_value(var: &Value, namespace: &str, objects: &[Value]) -> Option<String> {
+    if let Some(value) = var.get("value") {
+        return Some(match value {
+            Value::String(value) => value.clone(),
+            other => serde_yaml::to_string(other).ok()?.trim_end().to_string(),
+        });
+    }
+
+    let lookup = |kind: &str, reference: &Value| {
+        objects.iter().find(|o| {
+            o["kind"] == kind
+                && str_at(&o["metadata"]["namespace"]) == namespace
+                && o["metadata"]["name"] == reference["name"]
+        })
+    };
+    let from = &var["valueFrom"];
+    if let Some(secret) = lookup("Secret", &from["secretKeyRef"]) {
+        let key = str_at(&from["secretKeyRef"]["key"]);
+        if let Some(value) = secret["stringData"][key].as_str() {
+            return Some(value.into());
+        }
+        return decode_base64(str_at(&secret["data"][key]));
+    }
+    let key = str_at(&from["configMapKeyRef"]["key"]);
+    lookup("ConfigMap", &from["configMapKeyRef"])
+        .and_then(|config_map| config_map["data"][key].as_str().map(String::from))
+}
+
+/// Decodes the value of a Secret's `data`, `None` unless it is valid base64 of UTF-8 text.
+fn decode_base64(encoded: &str) -> Option<String> {
+    let encoded: String = encoded.split_ascii_whitespace().collect();
+    String::from_utf8(STANDARD.decode(encoded).ok()?).ok()
+}
+
+/// The container port a Service port routes to, if it is exposed by `container`.
+fn container_port(container: &Value, port: &Value) -> Option<u64> {
+    let target = match &port["targetPort"] {
+        Value::Null => &port["port"],
+        target => target,
+    };
+    sequence(&container["ports"]).iter().find_map(|p| {
+        let matches = match target {
+            Value::String(name) => p["name"].as_str() == Some(name),
+            number => p["containerPort"] == *number,
+        };
+        matches.then(|| p["containerPort"].as_u64()).flatten()
+    })
+}
+
+fn probe_port(container: &Value, port: &Value) -> String {
+    match port {
+        Value::String(name) => sequence(&container["ports"])
+            .iter()
+            .find(|p| p["name"].as_str() == Some(name))
+            .and_then(|p| p["containerPort"].as_u64())
+            .map(|port| port.to_string())
+            .unwrap_or_else(|| name.clone()),
+        other => other.as_u64().unwrap_or(80).to_string(),
+    }
+}
+
+/// Whether `value` mentions one of the DNS names of Service `host` in `namespace`.
+pub(crate) fn mentions_host(value: &str, host: &str, namespace: &str) -> bool {
+    rewrite_host(value, host, namespace, "") != value
+}
+
+/// Replaces the DNS names of Service `host` in `namespace`, from fully qualified down to the
+/// bare name, with the compose service `target`.
+fn rewrite_host(value: &str, host: &str, namespace: &str, target: &str) -> String {
+    let names = [
+        format!("{host}.{namespace}.svc.cluster.local"),
+        format!("{host}.{namespace}.svc"),
+        format!("{host}.{namespace}"),
+        host.to_string(),
+    ];
+    let is_dns = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '.';
+
+    let mut rewritten = String::default();
+    let mut rest = value;
+    'scan: while !rest.is_empty() {
+        let boundary = !rewritten.chars().last().is_some_and(is_dns);
+        if boundary {
+            for name in &names {
+                if let Some(after) = rest.strip_prefix(name.as_str()) {
+                    if !after.chars().next().is_some_and(is_dns) {
+                        rewritten.push_str(target);
+                        rest = after;
+                        continue 'scan;
+                    }
+                }
+            }
+        }
+        let next = rest.chars().next().expect("rest is not empty");
+        rewritten.push(next);
+        rest = &rest[next.len_utf8()..];
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::EnvironmentVariable, recipes::*};
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let api = Microservice::new(
+            "ghcr.io/example/api:1.0".into(),
+            "1.0".into(),
+            2,
+            "api".into(),
+            vec![
+                EnvironmentVariable::from((
+                    "DATABASE_URL",
+                    "postgres://postgres@example-db-svc.example.svc.cluster.local:5432/app",
+                )),
+                EnvironmentVariable::from(("AMQP_HOST", "example-mq-svc")),
+                EnvironmentVariable::from(("GREETING", "hello example-mq-svc-like")),
+            ],
+            vec![MicroservicePort::TCP {
+                port: 8080,
+                name: Some("http".into()),
+            }],
+        );
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::RabbitMQ(RabbitMQ::new("rabbitmq:3".into())))
+            .with_resource(Resource::Microservice(api))
+            .build();
+        let compose = stack.as_compose()?;
+
+        assert_eq!(
+            compose.services().keys().collect::<Vec<_>>(),
+            vec!["api", "db", "mq"]
+        );
+        let db = &compose.services()["db"];
+        assert_eq!(db.ports(), ["5432:5432"]);
+        assert_eq!(db.volumes(), ["db-vol:/var/lib/postgresql/data"]);
+        assert_eq!(db.environment()["POSTGRES_PASSWORD"], "postgres");
+        assert_eq!(
+            db.healthcheck().map(Healthcheck::test),
+            Some(&["CMD-SHELL".to_string(), "pg_isready -U postgres".into()][..])
+        );
+        assert_eq!(
+            compose.volumes().collect::<Vec<_>>(),
+            vec!["db-vol", "mq-vol"]
+        );
+
+        let api = &compose.services()["api"];
+        assert_eq!(
+            api.environment()["DATABASE_URL"],
+            "postgres://postgres@db:5432/app"
+        );
+        assert_eq!(api.environment()["AMQP_HOST"], "mq");
+        assert_eq!(api.environment()["GREETING"], "hello example-mq-svc-like");
+        assert_eq!(api.ports(), ["8080:8080"]);
+        assert!(api.healthcheck().is_none());
+
+        let yaml = compose.to_yaml()?;
+        assert!(yaml.starts_with("services:\n  api:\n    image: ghcr.io/example/api:1.0\n"));
+        assert!(yaml.contains("\nvolumes:\n  db-vol: {}\n  mq-vol: {}\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_secrets_and_probes() -> anyhow::Result<()> {
+        let objects: Vec<Value> = serde_yaml::from_str(
+            r#"
+            - apiVersion: v1
+              kind: Secret
+              metadata: {name: creds, namespace: dev}
+              data: {password: c2VjcmV0}
+            - apiVersion: apps/v1
+              kind: Deployment
+              metadata: {name: dev-app, namespace: dev}
+              spec:
+                template:
+                  spec:
+                    containers:
+                      - name: app
+                        image: app:1
+                        ports: [{name: http, containerPort: 8000}]
+                        env:
+                          - name: PASSWORD
+                            valueFrom: {secretKeyRef: {name: creds, key: password}}
+                        readinessProbe: {httpGet: {path: /healthz, port: http}}
+            "#,
+        )?;
+        let compose = Compose::from_objects(&objects);
+        let app = &compose.services()["app"];
+        assert_eq!(app.environment()["PASSWORD"], "secret");
+        assert_eq!(
+            app.healthcheck().unwrap().test()[1],
+            "curl -fsS http://localhost:8000/healthz > /dev/null || exit 1"
+        );
+        assert!(app.ports().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn moves_colliding_host_ports() -> anyhow::Result<()> {
+        let service = |role: &str| {
+            Resource::Microservice(Microservice::new(
+                format!("ghcr.io/example/{role}:1.0"),
+                "1.0".into(),
+                1,
+                role.into(),
+                Vec::default(),
+                vec![MicroservicePort::TCP {
+                    port: 8080,
+                    name: Some("http".into()),
+                }],
+            ))
+        };
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(service("api"))
+            .with_resource(service("web"))
+            .with_resource(service("admin"))
+            .build();
+        let compose = stack.as_compose()?;
+
+        let mut ports: Vec<&str> = compose
+            .services()
+            .values()
+            .flat_map(|service| service.ports().iter().map(String::as_str))
+            .collect();
+        ports.sort();
+        assert_eq!(ports, ["8080:8080", "8081:8080", "8082:8080"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_secret_data() {
+        assert_eq!(decode_base64("c2VjcmV0").as_deref(), Some("secret"));
+        assert_eq!(decode_base64("c2VjcmV0MQ==").as_deref(), Some("secret1"));
+        assert_eq!(decode_base64("c2Vj\ncmV0MTI=").as_deref(), Some("secret12"));
+        assert_eq!(decode_base64(""), Some(String::new()));
+        assert_eq!(decode_base64("c2VjcmV0MQ"), None);
+        assert_eq!(decode_base64("c2Vj*mV0"), None);
+        assert_eq!(decode_base64("/w=="), None);
+    }
+}
@@ -0,0 +1,505 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    check::{str_at, ObjectRef},
+    k8s::{image::ImageRef, validation::field},
+    lint::pod_spec,
+};
+
+/// Fields the API server fills in when a field is left out, by kind (`*` for any kind) and
+/// path, with `{pod}` standing for the pod spec and `[]` for any list item. Values are YAML.
+#[rustfmt::skip]
+const DEFAULTS: &[(&str, &str, &str)] = &[
+    ("Service", "spec.type", "ClusterIP"),
+    ("Service", "spec.sessionAffinity", "None"),
+    ("Service", "spec.ports[].protocol", "TCP"),
+    ("Deployment", "spec.replicas", "1"),
+    ("Deployment", "spec.revisionHistoryLimit", "10"),
+    ("Deployment", "spec.progressDeadlineSeconds", "600"),
+    ("StatefulSet", "spec.replicas", "1"),
+    ("StatefulSet", "spec.revisionHistoryLimit", "10"),
+    ("StatefulSet", "spec.podManagementPolicy", "OrderedReady"),
+    ("Secret", "type", "Opaque"),
+    ("*", "{pod}.restartPolicy", "Always"),
+    ("*", "{pod}.dnsPolicy", "ClusterFirst"),
+    ("*", "{pod}.schedulerName", "default-scheduler"),
+    ("*", "{pod}.terminationGracePeriodSeconds", "30"),
+    ("*", "{pod}.containers[].ports[].protocol", "TCP"),
+    ("*", "{pod}.containers[].terminationMessagePath", "/dev/termination-log"),
+    ("*", "{pod}.containers[].terminationMessagePolicy", "File"),
+    ("*", "{pod}.initContainers[].ports[].protocol", "TCP"),
+    ("*", "{pod}.initContainers[].terminationMessagePath", "/dev/termination-log"),
+    ("*", "{pod}.initContainers[].terminationMessagePolicy", "File"),
+];
+
+/// Metadata the API server maintains, which never matters when comparing.
+const SERVER_METADATA: &[&str] = &[
+    "uid",
+    "resourceVersion",
+    "generation",
+    "creationTimestamp",
+    "managedFields",
+    "selfLink",
+];
+
+const SERVER_ANNOTATIONS: &[&str] = &[
+    "kubectl.kubernetes.io/last-applied-configuration",
+    "deployment.kubernetes.io/revision",
+];
+
+/// The differences between two sets of objects. Objects are matched by apiVersion, kind,
+/// namespace and name; unchanged objects are left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Diff {
+    objects: Vec<ObjectDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectDiff {
+    api_version: String,
+    object: ObjectRef,
+    change: ObjectChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "fields")]
+pub enum ObjectChange {
+    Added,
+    Removed,
+    Modified(Vec<FieldChange>),
+}
+
+/// A field that was added (nothing `before`), removed (nothing `after`) or changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    path: String,
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+impl Diff {
+    pub fn objects(&self) -> &[ObjectDiff] {
+        &self.objects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn added(&self) -> impl Iterator<Item = &ObjectDiff> {
+        self.objects
+            .iter()
+            .filter(|o| matches!(o.change, ObjectChange::Added))
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = &ObjectDiff> {
+        self.objects
+            .iter()
+            .filter(|o| matches!(o.change, ObjectChange::Removed))
+    }
+
+    pub fn modified(&self) -> impl Iterator<Item = &ObjectDiff> {
+        self.objects
+            .iter()
+            .filter(|o| matches!(o.change, ObjectChange::Modified(_)))
+    }
+}
+
+impl ObjectDiff {
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    pub fn change(&self) -> &ObjectChange {
+        &self.change
+    }
+}
+
+impl FieldChange {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn before(&self) -> Option<&Value> {
+        self.before.as_ref()
+    }
+
+    pub fn after(&self) -> Option<&Value> {
+        self.after.as_ref()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for object in &self.objects {
+            write!(f, "{object}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ObjectDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.change {
+            ObjectChange::Added => '+',
+            ObjectChange::Removed => '-',
+            ObjectChange::Modified(_) => '~',
+        };
+        writeln!(f, "{sign} {} ({})", self.object, self.api_version)?;
+        if let ObjectChange::Modified(fields) = &self.change {
+            for change in fields {
+                writeln!(f, "    {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (Some(old), Some(new)) => {
+                write!(f, "{}: {} -> {}", self.path, inline(old), inline(new))
+            }
+            (None, Some(new)) => write!(f, "+ {}: {}", self.path, inline(new)),
+            (Some(old), None) => write!(f, "- {}: {}", self.path, inline(old)),
+            (None, None) => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Compares `old` with `new`. Map ordering, server-maintained metadata, `status` and fields
+/// left at their defaults are ignored, and lists of named items such as containers are matched
+/// by name.
+pub fn diff(old: &[Value], new: &[Value]) -> Diff {
+    let key = |object: &Value| {
+        (
+            str_at(&object["apiVersion"]).to_string(),
+            ObjectRef::of(object),
+        )
+    };
+    let old: Vec<_> = old.iter().map(|o| (key(o), normalize(o))).collect();
+    let new: Vec<_> = new.iter().map(|o| (key(o), normalize(o))).collect();
+
+    let mut objects = Vec::default();
+    for ((api_version, object), value) in &new {
+        let change = match old
+            .iter()
+            .find(|(k, _)| k.0 == *api_version && k.1 == *object)
+        {
+            None => ObjectChange::Added,
+            Some((_, previous)) => {
+                let mut fields = Vec::default();
+                compare("", previous, value, &mut fields);
+                if fields.is_empty() {
+                    continue;
+                }
+                ObjectChange::Modified(fields)
+            }
+        };
+        objects.push(ObjectDiff {
+            api_version: api_version.clone(),
+            object: object.clone(),
+            change,
+        });
+    }
+    for ((api_version, object), _) in &old {
+        if !new
+            .iter()
+            .any(|(k, _)| k.0 == *api_version && k.1 == *object)
+        {
+            objects.push(ObjectDiff {
+                api_version: api_version.clone(),
+                object: object.clone(),
+                change: ObjectChange::Removed,
+            });
+        }
+    }
+
+    Diff { objects }
+}
+
+fn compare(path: &str, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Mapping(old), Value::Mapping(new)) => {
+            for (key, value) in new {
+                let path = field(path, &key_name(key));
+                match old.get(key) {
+                    Some(previous) => compare(&path, previous, value, changes),
+                    None => changes.push(FieldChange::added(path, value)),
+                }
+            }
+            for (key, value) in old {
+                if !new.contains_key(key) {
+                    changes.push(FieldChange::removed(field(path, &key_name(key)), value));
+                }
+            }
+        }
+        (Value::Sequence(old), Value::Sequence(new)) => match (names(old), names(new)) {
+            (Some(old_names), Some(new_names)) => {
+                for (name, value) in new_names.iter().zip(new) {
+                    let path = format!("{path}[name={name}]");
+                    match old_names.iter().position(|n| n == name) {
+                        Some(i) => compare(&path, &old[i], value, changes),
+                        None => changes.push(FieldChange::added(path, value)),
+                    }
+                }
+                for (name, value) in old_names.iter().zip(old) {
+                    if !new_names.contains(name) {
+                        changes.push(FieldChange::removed(format!("{path}[name={name}]"), value));
+                    }
+                }
+            }
+            _ => {
+                for i in 0..old.len().max(new.len()) {
+                    let path = format!("{path}[{i}]");
+                    match (old.get(i), new.get(i)) {
+                        (Some(old), Some(new)) => compare(&path, old, new, changes),
+                        (None, Some(new)) => changes.push(FieldChange::added(path, new)),
+                        (Some(old), None) => changes.push(FieldChange::removed(path, old)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        },
+        _ if old != new => changes.push(FieldChange {
+            path: path.into(),
+            before: Some(old.clone()),
+            after: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+impl FieldChange {
+    fn added(path: String, value: &Value) -> Self {
+        FieldChange {
+            path,
+            before: None,
+            after: Some(value.clone()),
+        }
+    }
+
+    fn removed(path: String, value: &Value) -> Self {
+        FieldChange {
+            path,
+            before: Some(value.clone()),
+            after: None,
+        }
+    }
+}
+
+/// The `name` of every item, if all items are maps with distinct names.
+fn names(items: &[Value]) -> Option<Vec<&str>> {
+    let names: Vec<&str> = items
+        .iter()
+        .map(|item| item.get("name").and_then(Value::as_str))
+        .collect::<Option<_>>()?;
+    let distinct = names
+        .iter()
+        .enumerate()
+        .all(|(i, name)| !names[..i].contains(name));
+    distinct.then_some(names)
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        other => inline(other),
+    }
+}
+
+fn inline(value: &Value) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| format!("{value:?}"))
+}
+
+/// Strips everything [`diff`] ignores from a copy of `object`.
+fn normalize(object: &Value) -> Value {
+    let kind = str_at(&object["kind"]).to_string();
+    let pod = pod_spec(object).map(|(path, _)| path).unwrap_or_default();
+    let mut object = object.clone();
+
+    if let Value::Mapping(mapping) = &mut object {
+        mapping.remove("status");
+        if let Some(Value::Mapping(metadata)) = mapping.get_mut("metadata") {
+            for key in SERVER_METADATA {
+                metadata.remove(*key);
+            }
+            if let Some(Value::Mapping(annotations)) = metadata.get_mut("annotations") {
+                for key in SERVER_ANNOTATIONS {
+                    annotations.remove(*key);
+                }
+            }
+        }
+    }
+    strip(&mut object, "", &kind, pod);
+    object
+}
+
+fn strip(value: &mut Value, path: &str, kind: &str, pod: &str) {
+    match value {
+        Value::Mapping(mapping) => {
+            if path.ends_with("containers[]") || path.ends_with("Containers[]") {
+                strip_image_pull_policy(mapping);
+            }
+            if kind == "Service"
+                && path == "spec.ports[]"
+                && mapping.get("targetPort") == mapping.get("port")
+            {
+                mapping.remove("targetPort");
+            }
+            mapping.retain(|key, child| {
+                let path = field(path, &key_name(key));
+                strip(child, &path, kind, pod);
+                !is_empty(child) && !is_default(kind, pod, &path, child)
+            });
+        }
+        Value::Sequence(items) => {
+            let path = format!("{path}[]");
+            for item in items {
+                strip(item, &path, kind, pod);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `imagePullPolicy` defaults to `Always` for unpinned images and `IfNotPresent` otherwise.
+fn strip_image_pull_policy(container: &mut Mapping) {
+    let image = container.get("image").map(str_at).unwrap_or_default();
+    let default = match ImageRef::parse(image) {
+        Ok(image) if !image.is_pinned() => "Always",
+        _ => "IfNotPresent",
+    };
+    if container.get("imagePullPolicy").map(str_at) == Some(default) {
+        container.remove("imagePullPolicy");
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Mapping(mapping) => mapping.is_empty(),
+        Value::Sequence(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_default(kind: &str, pod: &str, path: &str, value: &Value) -> bool {
+    DEFAULTS.iter().any(|(k, p, default)| {
+        (*k == "*" || *k == kind)
+            && (!p.starts_with("{pod}") || !pod.is_empty())
+            && p.replace("{pod}", pod) == path
+            && serde_yaml::from_str::<Value>(default).ok().as_ref() == Some(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{directory::DirectoryWriter, recipes::*};
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let before = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.26".into(), 2)))
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .build();
+        let after = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 3)))
+            .with_resource(Resource::RabbitMQ(RabbitMQ::new("rabbitmq:3".into())))
+            .build();
+
+        let diff = before.diff(&after)?;
+        assert_eq!(
+            diff.to_string(),
+            "+ Service example/example-mq-svc (v1)
+~ Deployment example/example-web (apps/v1)
+    spec.replicas: 2 -> 3
+    spec.template.spec.containers[name=example-web].image: \"nginx:1.26\" -> \"nginx:1.27\"
++ StatefulSet example/example-mq (apps/v1)
+- Service example/example-db-svc (v1)
+- StatefulSet example/example-db (apps/v1)
+"
+        );
+        assert_eq!(diff.added().count(), 2);
+        assert!(before.diff(&before)?.is_empty());
+
+        let dir = tempfile::tempdir()?;
+        before.write_dir(&DirectoryWriter::new(dir.path()))?;
+        assert!(before.diff_dir(dir.path())?.is_empty());
+        assert_eq!(after.diff_dir(dir.path())?, diff);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_defaults_and_server_fields() -> anyhow::Result<()> {
+        let rendered: Value = serde_yaml::from_str(
+            r#"
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata: {name: web, namespace: example, labels: {app: web, tier: front}}
+            spec:
+              selector: {matchLabels: {app: web}}
+              template:
+                spec:
+                  containers:
+                    - {name: web, image: "nginx:1.27", ports: [{containerPort: 80}]}
+                    - {name: proxy, image: envoy}
+            "#,
+        )?;
+        let live: Value = serde_yaml::from_str(
+            r#"
+            apiVersion: apps/v1
+            kind: Deployment
+            metadata:
+              labels: {tier: front, app: web}
+              name: web
+              namespace: example
+              uid: 5b8e3c1a
+              resourceVersion: "12345"
+              annotations: {deployment.kubernetes.io/revision: "3"}
+            spec:
+              replicas: 1
+              revisionHistoryLimit: 10
+              selector: {matchLabels: {app: web}}
+              template:
+                metadata: {}
+                spec:
+                  restartPolicy: Always
+                  containers:
+                    - name: proxy
+                      image: envoy
+                      imagePullPolicy: Always
+                    - name: web
+                      image: "nginx:1.27"
+                      imagePullPolicy: IfNotPresent
+                      ports: [{containerPort: 80, protocol: TCP}]
+            status: {replicas: 1}
+            "#,
+        )?;
+        assert!(diff(std::slice::from_ref(&live), std::slice::from_ref(&rendered)).is_empty());
+
+        let mut changed = rendered;
+        changed["spec"]["template"]["spec"]["containers"][0]["ports"][0]["protocol"] = "UDP".into();
+        let diff = diff(&[live], &[changed]);
+        let ObjectChange::Modified(fields) = diff.objects()[0].change() else {
+            panic!("expected a modification");
+        };
+        assert_eq!(
+            fields[0].to_string(),
+            "+ spec.template.spec.containers[name=web].ports[0].protocol: \"UDP\""
+        );
+
+        Ok(())
+    }
+}
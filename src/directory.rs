@@ -0,0 +1,373 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_yaml::Value;
+use thiserror::Error;
+
+use crate::{
+    check::str_at,
+    yaml::{YamlError, YamlWriter},
+};
+
+/// First line of every file the directory writer owns. Files without it are never touched.
+pub const GENERATED_MARKER: &str = "# Generated by flagship. Do not edit.";
+
+const KUSTOMIZATION: &str = "kustomization.yaml";
+
+/// Lists the files the last run wrote, so the next one only removes its own output and leaves
+/// generated files of other writers, e.g. in a child directory, alone.
+const STATE_FILE: &str = ".flagship-generated";
+
+/// Characters that separate placeholders, dropped together with a placeholder that is empty.
+const SEPARATORS: &[char] = &['-', '_', '.'];
+
+#[derive(Debug, Error)]
+pub enum DirectoryError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("YamlError: {0}")]
+    YamlError(#[from] YamlError),
+    #[error("DuplicatePathError: more than one object is written to {0}")]
+    DuplicatePathError(PathBuf),
+    #[error("InvalidPathError: {0:?} does not stay inside the output directory")]
+    InvalidPathError(String),
+    #[error("UnmanagedFileError: {0} exists and was not generated by flagship")]
+    UnmanagedFileError(PathBuf),
+}
+
+pub type DirectoryResult<T> = Result<T, DirectoryError>;
+
+/// Where each object is written, relative to the output directory, as a template of
+/// `{kind}` (lower case), `{name}`, `{namespace}` and `{group}` (empty for the core group).
+/// An empty placeholder, like the namespace of a cluster-scoped object, is dropped with one
+/// separator next to it, and so is a directory that ends up without a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamingScheme(String);
+
+/// Writes objects one file per object below a directory, with a `kustomization.yaml` listing
+/// them. Files the writer generated for the same directory earlier that are no longer produced
+/// are deleted; the writer keeps track of them in a `.flagship-generated` file.
+#[derive(Debug, Clone)]
+pub struct DirectoryWriter {
+    root: PathBuf,
+    naming: NamingScheme,
+    kustomization: bool,
+}
+
+/// What a [`DirectoryWriter`] changed, with paths relative to the output directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryReport {
+    written: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Kustomization<'a> {
+    api_version: &'a str,
+    kind: &'a str,
+    resources: Vec<String>,
+}
+
+impl NamingScheme {
+    pub fn new<S: Into<String>>(template: S) -> Self {
+        NamingScheme(template.into())
+    }
+
+    pub fn path(&self, object: &Value) -> DirectoryResult<PathBuf> {
+        let group = str_at(&object["apiVersion"])
+            .rsplit_once('/')
+            .map(|(group, _)| group)
+            .unwrap_or_default();
+        let kind = str_at(&object["kind"]).to_lowercase();
+        let values = [
+            ("{kind}", kind.as_str()),
+            ("{name}", str_at(&object["metadata"]["name"])),
+            ("{namespace}", str_at(&object["metadata"]["namespace"])),
+            ("{group}", group),
+        ];
+        let path = self
+            .0
+            .split('/')
+            .map(|component| fill(component, &values))
+            .filter(|component| !component.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        relative_path(path)
+    }
+}
+
+/// Substitutes `values` into one component of a naming scheme.
+fn fill(component: &str, values: &[(&str, &str)]) -> String {
+    const EMPTY: char = '\0';
+    let mut filled = values
+        .iter()
+        .fold(component.to_string(), |filled, (placeholder, value)| {
+            let value = match *value {
+                "" => EMPTY.to_string(),
+                value => value.to_string(),
+            };
+            filled.replace(placeholder, &value)
+        });
+    while let Some(i) = filled.find(EMPTY) {
+        if filled[i + 1..].starts_with(SEPARATORS) {
+            filled.replace_range(i..i + 2, "");
+        } else if filled[..i].ends_with(SEPARATORS) {
+            filled.replace_range(i - 1..i + 1, "");
+        } else {
+            filled.replace_range(i..i + 1, "");
+        }
+    }
+    filled
+}
+
+/// `path` if it is relative and stays inside the directory it is relative to.
+fn relative_path(path: String) -> DirectoryResult<PathBuf> {
+    let relative = Path::new(&path);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !inside || path.is_empty() {
+        return Err(DirectoryError::InvalidPathError(path));
+    }
+    Ok(relative.to_path_buf())
+}
+
+impl Default for NamingScheme {
+    fn default() -> Self {
+        NamingScheme::new("{kind}/{name}.yaml")
+    }
+}
+
+impl DirectoryReport {
+    pub fn written(&self) -> &[PathBuf] {
+        &self.written
+    }
+
+    pub fn removed(&self) -> &[PathBuf] {
+        &self.removed
+    }
+}
+
+impl DirectoryWriter {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DirectoryWriter {
+            root: root.into(),
+            naming: NamingScheme::default(),
+            kustomization: true,
+        }
+    }
+
+    pub fn with_naming_scheme(mut self, naming: NamingScheme) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn with_kustomization(mut self, kustomization: bool) -> Self {
+        self.kustomization = kustomization;
+        self
+    }
+
+    pub fn write(&self, objects: &[Value]) -> DirectoryResult<DirectoryReport> {
+        let mut files = BTreeMap::default();
+        for object in objects {
+            let path = self.naming.path(object)?;
+            let reserved = path == Path::new(KUSTOMIZATION) || path == Path::new(STATE_FILE);
+            if reserved || files.contains_key(&path) {
+                return Err(DirectoryError::DuplicatePathError(path));
+            }
+            files.insert(path, render(std::slice::from_ref(object))?);
+        }
+
+        if self.kustomization {
+            let kustomization = Kustomization {
+                api_version: "kustomize.config.k8s.io/v1beta1",
+                kind: "Kustomization",
+                resources: files
+                    .keys()
+                    .map(|path| path.to_string_lossy().replace('\\', "/"))
+                    .collect(),
+            };
+            files.insert(KUSTOMIZATION.into(), render(&[kustomization])?);
+        }
+
+        // Check everything up front so a conflict leaves the directory as it was.
+        for path in files.keys() {
+            let path = self.root.join(path);
+            if path.exists() && !is_generated(&path)? {
+                return Err(DirectoryError::UnmanagedFileError(path));
+            }
+        }
+
+        let mut report = DirectoryReport::default();
+        for relative in self.previous()? {
+            let path = self.root.join(&relative);
+            if !files.contains_key(&relative) && path.is_file() && is_generated(&path)? {
+                fs::remove_file(&path)?;
+                remove_empty_parents(&self.root, &path)?;
+                report.removed.push(relative);
+            }
+        }
+
+        for (relative, contents) in files {
+            let path = self.root.join(&relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, contents)?;
+            report.written.push(relative);
+        }
+
+        let mut state = format!("{GENERATED_MARKER}\n");
+        for relative in &report.written {
+            state.push_str(&relative.to_string_lossy().replace('\\', "/"));
+            state.push('\n');
+        }
+        fs::write(self.root.join(STATE_FILE), state)?;
+
+        Ok(report)
+    }
+
+    /// The files the last run wrote, as recorded in the state file.
+    fn previous(&self) -> DirectoryResult<Vec<PathBuf>> {
+        let state = self.root.join(STATE_FILE);
+        if !state.is_file() || !is_generated(&state)? {
+            return Ok(Vec::default());
+        }
+        Ok(fs::read_to_string(state)?
+            .lines()
+            .skip(1)
+            .filter_map(|line| relative_path(line.to_string()).ok())
+            .collect())
+    }
+}
+
+fn render<T: Serialize>(objects: &[T]) -> DirectoryResult<String> {
+    let mut writer = YamlWriter::new(Vec::default()).with_header_line(&GENERATED_MARKER[2..]);
+    writer.write_all(objects)?;
+    Ok(String::from_utf8(writer.finish()?).expect("serde_yaml writes UTF-8"))
+}
+
+fn is_generated(path: &Path) -> DirectoryResult<bool> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents.lines().next() == Some(GENERATED_MARKER))
+}
+
+fn remove_empty_parents(root: &Path, path: &Path) -> DirectoryResult<()> {
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == root || fs::read_dir(current)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(current)?;
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::*;
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("README.md"), "hand written")?;
+
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .build();
+        let writer = DirectoryWriter::new(dir.path());
+        let report = stack.write_dir(&writer)?;
+
+        let written: Vec<&str> = report.written().iter().filter_map(|p| p.to_str()).collect();
+        assert_eq!(
+            written,
+            vec![
+                "deployment/example-web.yaml",
+                "kustomization.yaml",
+                "namespace/example.yaml",
+                "service/example-db-svc.yaml",
+                "service/example-web-svc.yaml",
+                "statefulset/example-db.yaml",
+            ]
+        );
+        let kustomization = fs::read_to_string(dir.path().join("kustomization.yaml"))?;
+        assert!(kustomization.starts_with(GENERATED_MARKER));
+        assert!(kustomization.contains("- statefulset/example-db.yaml\n"));
+
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+        let report = stack.write_dir(&writer)?;
+        let removed: Vec<&str> = report.removed().iter().filter_map(|p| p.to_str()).collect();
+        assert_eq!(
+            removed,
+            vec!["service/example-db-svc.yaml", "statefulset/example-db.yaml"]
+        );
+        assert!(!dir.path().join("statefulset").exists());
+        assert!(dir.path().join("README.md").exists());
+
+        fs::write(dir.path().join("namespace/example.yaml"), "kind: Namespace")?;
+        assert!(matches!(
+            stack.write_dir(&writer),
+            Err(StackError::DirectoryError(
+                DirectoryError::UnmanagedFileError(_)
+            ))
+        ));
+
+        let flat = DirectoryWriter::new(dir.path().join("flat"))
+            .with_naming_scheme(NamingScheme::new("{namespace}-{kind}-{name}.yml"))
+            .with_kustomization(false);
+        let report = stack.write_dir(&flat)?;
+        let written: Vec<&str> = report.written().iter().filter_map(|p| p.to_str()).collect();
+        assert!(written.contains(&"example-deployment-example-web.yml"));
+        assert!(written.contains(&"namespace-example.yml"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_other_writers_alone() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let stack = |name: &str, database: bool| {
+            let stack = Stack::builder(name, true, Environment::Production)
+                .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)));
+            match database {
+                true => {
+                    stack.with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+                }
+                false => stack,
+            }
+            .build()
+        };
+
+        stack("staging", true).write_dir(&DirectoryWriter::new(dir.path().join("staging")))?;
+        let sibling = DirectoryWriter::new(dir.path().join("prod"));
+        stack("prod", true).write_dir(&sibling)?;
+        let root = DirectoryWriter::new(dir.path());
+        let report = stack("example", true).write_dir(&root)?;
+        assert!(report.removed().is_empty());
+
+        let report = stack("example", false).write_dir(&root)?;
+        let removed: Vec<&str> = report.removed().iter().filter_map(|p| p.to_str()).collect();
+        assert_eq!(
+            removed,
+            vec!["service/example-db-svc.yaml", "statefulset/example-db.yaml"]
+        );
+        for name in ["staging", "prod"] {
+            let database = dir
+                .path()
+                .join(format!("{name}/statefulset/{name}-db.yaml"));
+            assert!(database.exists());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+use std::{collections::BTreeSet, fmt};
+
+use serde::Serialize;
+use serde_yaml::Value;
+
+use crate::{
+    check::{selects, sequence, str_at, ObjectRef},
+    compose::mentions_host,
+    lint::pod_spec,
+};
+
+/// How objects of a stack relate to each other, for drawing diagrams.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Graph {
+    nodes: Vec<ObjectRef>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    from: ObjectRef,
+    to: ObjectRef,
+    kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// A Service selecting the pods of a workload.
+    Selects,
+    /// A workload mounting a ConfigMap, Secret or PersistentVolumeClaim.
+    Mounts,
+    /// A workload reading environment variables from a ConfigMap or Secret.
+    Reads,
+    /// An Ingress routing to a Service.
+    Routes,
+    /// A workload whose environment names a Service, such as a database URL.
+    Connects,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeKind::Selects => write!(f, "selects"),
+            EdgeKind::Mounts => write!(f, "mounts"),
+            EdgeKind::Reads => write!(f, "reads"),
+            EdgeKind::Routes => write!(f, "routes"),
+            EdgeKind::Connects => write!(f, "connects"),
+        }
+    }
+}
+
+impl Edge {
+    pub fn from(&self) -> &ObjectRef {
+        &self.from
+    }
+
+    pub fn to(&self) -> &ObjectRef {
+        &self.to
+    }
+
+    pub fn kind(&self) -> EdgeKind {
+        self.kind
+    }
+}
+
+impl Graph {
+    /// Derives the graph of rendered objects. Namespaces become groups rather than nodes, and
+    /// the claims of StatefulSet volume claim templates are added as PersistentVolumeClaims.
+    pub fn from_objects(objects: &[Value]) -> Self {
+        let mut graph = Graph::default();
+        for object in objects.iter().filter(|o| o["kind"] != "Namespace") {
+            graph.node(ObjectRef::of(object));
+        }
+
+        for object in objects {
+            let from = ObjectRef::of(object);
+            let namespace = from.namespace().to_string();
+            let reference =
+                |kind: &str, name: &Value| ObjectRef::new(kind, &namespace, str_at(name));
+
+            match str_at(&object["kind"]) {
+                "Service" => {
+                    let Some(selector) = object["spec"]["selector"].as_mapping() else {
+                        continue;
+                    };
+                    for workload in objects.iter().filter(|o| pod_spec(o).is_some()) {
+                        let (path, _) = pod_spec(workload).expect("filtered to workloads");
+                        let labels = pod_labels(workload, path);
+                        let to = ObjectRef::of(workload);
+                        if !selector.is_empty()
+                            && to.namespace() == namespace
+                            && selects(selector, labels)
+                        {
+                            graph.edge(from.clone(), to, EdgeKind::Selects);
+                        }
+                    }
+                }
+                "Ingress" => {
+                    let spec = &object["spec"];
+                    let mut backends = vec![&spec["defaultBackend"]];
+                    for rule in sequence(&spec["rules"]) {
+                        for path in sequence(&rule["http"]["paths"]) {
+                            backends.push(&path["backend"]);
+                        }
+                    }
+                    for backend in backends {
+                        let name = &backend["service"]["name"];
+                        if name.is_string() {
+                            graph.edge(from.clone(), reference("Service", name), EdgeKind::Routes);
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            let Some((_, pod)) = pod_spec(object) else {
+                continue;
+            };
+            for volume in sequence(&pod["volumes"]) {
+                let mounted = [
+                    ("ConfigMap", &volume["configMap"]["name"]),
+                    ("Secret", &volume["secret"]["secretName"]),
+                    (
+                        "PersistentVolumeClaim",
+                        &volume["persistentVolumeClaim"]["claimName"],
+                    ),
+                ];
+                for (kind, name) in mounted.into_iter().filter(|(_, name)| name.is_string()) {
+                    graph.edge(from.clone(), reference(kind, name), EdgeKind::Mounts);
+                }
+            }
+            for claim in sequence(&object["spec"]["volumeClaimTemplates"]) {
+                let claim = reference("PersistentVolumeClaim", &claim["metadata"]["name"]);
+                graph.node(claim.clone());
+                graph.edge(from.clone(), claim, EdgeKind::Mounts);
+            }
+
+            for container in ["initContainers", "containers"]
+                .into_iter()
+                .flat_map(|kind| sequence(&pod[kind]))
+            {
+                for var in sequence(&container["env"]) {
+                    let from_ref = &var["valueFrom"];
+                    let read = [
+                        ("ConfigMap", &from_ref["configMapKeyRef"]["name"]),
+                        ("Secret", &from_ref["secretKeyRef"]["name"]),
+                    ];
+                    for (kind, name) in read.into_iter().filter(|(_, name)| name.is_string()) {
+                        graph.edge(from.clone(), reference(kind, name), EdgeKind::Reads);
+                    }
+
+                    let Some(value) = var["value"].as_str() else {
+                        continue;
+                    };
+                    for service in objects.iter().filter(|o| o["kind"] == "Service") {
+                        let to = ObjectRef::of(service);
+                        if mentions_host(value, to.name(), to.namespace()) {
+                            graph.edge(from.clone(), to, EdgeKind::Connects);
+                        }
+                    }
+                }
+                for source in sequence(&container["envFrom"]) {
+                    let read = [
+                        ("ConfigMap", &source["configMapRef"]["name"]),
+                        ("Secret", &source["secretRef"]["name"]),
+                    ];
+                    for (kind, name) in read.into_iter().filter(|(_, name)| name.is_string()) {
+                        graph.edge(from.clone(), reference(kind, name), EdgeKind::Reads);
+                    }
+                }
+            }
+        }
+
+        graph
+    }
+
+    pub fn nodes(&self) -> &[ObjectRef] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Renders the graph in Graphviz DOT, with one cluster per namespace.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph stack {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (namespace, nodes) in self.by_namespace() {
+            let indent = if namespace.is_empty() {
+                "    "
+            } else {
+                dot.push_str(&format!(
+                    "    subgraph {} {{\n        label={};\n",
+                    quote(&format!("cluster_{namespace}")),
+                    quote(namespace)
+                ));
+                "        "
+            };
+            for node in nodes {
+                dot.push_str(&format!(
+                    "{indent}{} [label={}];\n",
+                    quote(&self.id(node)),
+                    quote(&format!("{}\\n{}", node.kind(), node.name()))
+                ));
+            }
+            if !namespace.is_empty() {
+                dot.push_str("    }\n");
+            }
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "    {} -> {} [label={}];\n",
+                quote(&self.id(&edge.from)),
+                quote(&self.id(&edge.to)),
+                quote(&edge.kind.to_string())
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as a Mermaid flowchart, with one subgraph per namespace.
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        for (namespace, nodes) in self.by_namespace() {
+            let indent = if namespace.is_empty() {
+                "    "
+            } else {
+                mermaid.push_str(&format!(
+                    "    subgraph ns_{}[\"{namespace}\"]\n",
+                    mermaid_id(namespace)
+                ));
+                "        "
+            };
+            for node in nodes {
+                mermaid.push_str(&format!(
+                    "{indent}{}[\"{}<br/>{}\"]\n",
+                    self.mermaid_node(node),
+                    node.kind(),
+                    node.name()
+                ));
+            }
+            if !namespace.is_empty() {
+                mermaid.push_str("    end\n");
+            }
+        }
+        for edge in &self.edges {
+            mermaid.push_str(&format!(
+                "    {} -->|{}| {}\n",
+                self.mermaid_node(&edge.from),
+                edge.kind,
+                self.mermaid_node(&edge.to)
+            ));
+        }
+        mermaid
+    }
+
+    fn node(&mut self, node: ObjectRef) {
+        if !self.nodes.contains(&node) {
+            self.nodes.push(node);
+        }
+    }
+
+    /// Adds an edge to `to` if it is part of the graph; references to objects outside the
+    /// stack are left to [`check`](crate::check::check).
+    fn edge(&mut self, from: ObjectRef, to: ObjectRef, kind: EdgeKind) {
+        let edge = Edge { from, to, kind };
+        if self.nodes.contains(&edge.to) && !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    fn by_namespace(&self) -> Vec<(&str, Vec<&ObjectRef>)> {
+        let namespaces: BTreeSet<&str> = self.nodes.iter().map(ObjectRef::namespace).collect();
+        namespaces
+            .into_iter()
+            .map(|namespace| {
+                let nodes = self
+                    .nodes
+                    .iter()
+                    .filter(|n| n.namespace() == namespace)
+                    .collect();
+                (namespace, nodes)
+            })
+            .collect()
+    }
+
+    fn id(&self, node: &ObjectRef) -> String {
+        format!("{}/{}/{}", node.kind(), node.namespace(), node.name())
+    }
+
+    fn mermaid_node(&self, node: &ObjectRef) -> String {
+        let position = self
+            .nodes
+            .iter()
+            .position(|n| n == node)
+            .unwrap_or_default();
+        format!("n{position}")
+    }
+}
+
+/// The labels of the pods a workload creates.
+pub(crate) fn pod_labels<'a>(workload: &'a Value, pod_path: &str) -> &'a Value {
+    match pod_path.strip_suffix(".spec") {
+        Some(template) => template
+            .split('.')
+            .fold(workload, |value, key| &value[key])
+            .get("metadata")
+            .map(|metadata| &metadata["labels"])
+            .unwrap_or(&Value::Null),
+        None => &workload["metadata"]["labels"],
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+fn mermaid_id(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::EnvironmentVariable, recipes::*};
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let api = Microservice::new(
+            "ghcr.io/example/api:1.0".into(),
+            "1.0".into(),
+            1,
+            "api".into(),
+            vec![EnvironmentVariable::from((
+                "DATABASE_HOST",
+                "example-db-svc.example.svc",
+            ))],
+            vec![],
+        );
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::Microservice(api))
+            .build();
+        let graph = stack.graph()?;
+
+        let edges: Vec<String> = graph
+            .edges()
+            .iter()
+            .map(|e| format!("{} {} {}", e.from(), e.kind(), e.to()))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                "Service example/example-db-svc selects StatefulSet example/example-db",
+                "Deployment example/example-api connects Service example/example-db-svc",
+                "StatefulSet example/example-db mounts PersistentVolumeClaim example/example-db-vol",
+            ]
+        );
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("    subgraph \"cluster_example\" {\n        label=\"example\";\n"));
+        assert!(dot.contains(
+            "    \"Service/example/example-db-svc\" -> \"StatefulSet/example/example-db\" [label=\"selects\"];\n"
+        ));
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n    subgraph ns_example[\"example\"]\n"));
+        assert!(mermaid.contains("        n0[\"Service<br/>example-db-svc\"]\n"));
+        assert!(mermaid.contains("    n0 -->|selects| n2\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mounts_and_routes() -> anyhow::Result<()> {
+        let objects: Vec<Value> = serde_yaml::from_str(
+            r#"
+            - {apiVersion: v1, kind: ConfigMap, metadata: {name: conf, namespace: a}}
+            - {apiVersion: v1, kind: Secret, metadata: {name: creds, namespace: a}}
+            - apiVersion: v1
+              kind: Service
+              metadata: {name: web, namespace: a}
+              spec: {selector: {app: web}}
+            - apiVersion: networking.k8s.io/v1
+              kind: Ingress
+              metadata: {name: web, namespace: a}
+              spec:
+                rules:
+                  - http: {paths: [{path: /, backend: {service: {name: web}}}]}
+            - apiVersion: apps/v1
+              kind: Deployment
+              metadata: {name: web, namespace: a}
+              spec:
+                template:
+                  metadata: {labels: {app: web}}
+                  spec:
+                    volumes: [{name: conf, configMap: {name: conf}}]
+                    containers:
+                      - name: web
+                        envFrom: [{secretRef: {name: creds}}]
+            "#,
+        )?;
+        let graph = Graph::from_objects(&objects);
+        let kinds: Vec<EdgeKind> = graph.edges().iter().map(Edge::kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EdgeKind::Selects,
+                EdgeKind::Routes,
+                EdgeKind::Mounts,
+                EdgeKind::Reads
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use crate::{
+    check::{sequence, str_at},
+    directory::{DirectoryError, NamingScheme},
+    lint::pod_spec,
+};
+
+#[derive(Debug, Error)]
+pub enum HelmError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("SerdeYamlError: {0}")]
+    SerdeYamlError(#[from] serde_yaml::Error),
+    #[error("DirectoryError: {0}")]
+    DirectoryError(#[from] DirectoryError),
+    #[error("DuplicateValueError: {0} is set by more than one object")]
+    DuplicateValueError(String),
+}
+
+pub type HelmResult<T> = Result<T, HelmError>;
+
+/// Turns rendered objects into a Helm chart whose templates reproduce them exactly with the
+/// default values.
+///
+/// Values are keyed by workload name without the namespace prefix, so `example-web` in
+/// namespace `example` is configured under `web`. Each workload exposes `replicas`, and its
+/// container's `image` and `env` (under `containers.<name>` when there is more than one);
+/// `storage` holds the volume claim template size (a map by claim name when there is more than
+/// one).
+#[derive(Debug, Clone)]
+pub struct HelmExporter {
+    chart: ChartMetadata,
+    naming: NamingScheme,
+}
+
+/// The files of a Helm chart, by path relative to the chart directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelmChart {
+    files: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMetadata {
+    api_version: &'static str,
+    name: String,
+    description: String,
+    #[serde(rename = "type")]
+    chart_type: &'static str,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    app_version: Option<String>,
+}
+
+/// Replaces configurable fields with placeholders and collects their defaults.
+#[derive(Debug, Default)]
+struct Templater {
+    values: Mapping,
+    placeholders: Vec<(String, String)>,
+}
+
+impl HelmExporter {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        HelmExporter {
+            chart: ChartMetadata {
+                api_version: "v2",
+                name: name.into(),
+                description: "Generated by flagship".into(),
+                chart_type: "application",
+                version: "0.1.0".into(),
+                app_version: None,
+            },
+            naming: NamingScheme::new("templates/{kind}-{name}.yaml"),
+        }
+    }
+
+    pub fn with_version<S: Into<String>>(mut self, version: S) -> Self {
+        self.chart.version = version.into();
+        self
+    }
+
+    pub fn with_app_version<S: Into<String>>(mut self, app_version: S) -> Self {
+        self.chart.app_version = Some(app_version.into());
+        self
+    }
+
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.chart.description = description.into();
+        self
+    }
+
+    /// Where templates are written; paths should start with `templates/`.
+    pub fn with_naming_scheme(mut self, naming: NamingScheme) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn export(&self, objects: &[Value]) -> HelmResult<HelmChart> {
+        let mut templater = Templater::default();
+        let mut files = BTreeMap::default();
+        files.insert("Chart.yaml".into(), serde_yaml::to_string(&self.chart)?);
+
+        for object in objects {
+            let path = self.naming.path(object)?;
+            let template = templater.template(object)?;
+            if files.insert(path.clone(), template).is_some() {
+                return Err(DirectoryError::DuplicatePathError(path).into());
+            }
+        }
+
+        files.insert(
+            "values.yaml".into(),
+            serde_yaml::to_string(&templater.values)?,
+        );
+        Ok(HelmChart { files })
+    }
+}
+
+impl HelmChart {
+    pub fn files(&self) -> &BTreeMap<PathBuf, String> {
+        &self.files
+    }
+
+    pub fn values(&self) -> &str {
+        &self.files[Path::new("values.yaml")]
+    }
+
+    /// Writes the chart below `dir`, creating it if needed.
+    pub fn write<P: AsRef<Path>>(&self, dir: P) -> HelmResult<()> {
+        for (relative, contents) in &self.files {
+            let path = dir.as_ref().join(relative);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+        Ok(())
+    }
+}
+
+impl Templater {
+    fn template(&mut self, object: &Value) -> HelmResult<String> {
+        let mut object = object.clone();
+        let namespace = str_at(&object["metadata"]["namespace"]).to_string();
+        let component = short_name(str_at(&object["metadata"]["name"]), &namespace);
+
+        if let Some((pod_path, _)) = pod_spec(&object) {
+            if object["spec"]["replicas"].is_number() {
+                let replicas = &mut object["spec"]["replicas"];
+                self.placeholder(replicas, &[&component, "replicas"], false)?;
+            }
+
+            let containers = pod_path
+                .split('.')
+                .chain(["containers"])
+                .try_fold(&mut object, |value, key| value.get_mut(key))
+                .and_then(Value::as_sequence_mut);
+            if let Some(containers) = containers {
+                let single = containers.len() == 1;
+                for container in containers {
+                    let name = short_name(str_at(&container["name"]), &namespace);
+                    let mut keys = vec![component.as_str()];
+                    if !single {
+                        keys.extend(["containers", name.as_str()]);
+                    }
+                    self.container(container, &keys)?;
+                }
+            }
+
+            let claims = object
+                .get_mut("spec")
+                .and_then(|spec| spec.get_mut("volumeClaimTemplates"))
+                .and_then(Value::as_sequence_mut);
+            if let Some(claims) = claims {
+                let single = claims.len() == 1;
+                for claim in claims {
+                    let name = short_name(str_at(&claim["metadata"]["name"]), &namespace);
+                    let mut keys = vec![component.as_str(), "storage"];
+                    if !single {
+                        keys.push(name.as_str());
+                    }
+                    let storage = ["spec", "resources", "requests", "storage"]
+                        .into_iter()
+                        .try_fold(claim, |value, key| value.get_mut(key));
+                    if let Some(storage) = storage {
+                        self.placeholder(storage, &keys, true)?;
+                    }
+                }
+            }
+        }
+
+        let mut template = serde_yaml::to_string(&object)?;
+        for (placeholder, expression) in self.placeholders.drain(..) {
+            template = template.replace(&placeholder, &format!("{{{{ {expression} }}}}"));
+        }
+        Ok(template)
+    }
+
+    fn container(&mut self, container: &mut Value, keys: &[&str]) -> HelmResult<()> {
+        if container["image"].is_string() {
+            let image_keys = [keys, &["image"]].concat();
+            self.placeholder(&mut container["image"], &image_keys, true)?;
+        }
+        let names: Vec<String> = sequence(&container["env"])
+            .iter()
+            .map(|env| str_at(&env["name"]).to_string())
+            .collect();
+        if let Some(env) = container.get_mut("env").and_then(Value::as_sequence_mut) {
+            for (var, name) in env.iter_mut().zip(&names) {
+                if var["value"].is_string() {
+                    let env_keys = [keys, &["env", name]].concat();
+                    self.placeholder(&mut var["value"], &env_keys, true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves `value` into the values under `keys` and leaves a placeholder in its place.
+    fn placeholder(&mut self, value: &mut Value, keys: &[&str], quote: bool) -> HelmResult<()> {
+        let (last, parents) = keys.split_last().expect("values are keyed");
+        let mut mapping = &mut self.values;
+        for key in parents {
+            let entry = mapping
+                .entry(Value::from(*key))
+                .or_insert_with(|| Value::Mapping(Mapping::default()));
+            mapping = match entry {
+                Value::Mapping(mapping) => mapping,
+                _ => return Err(HelmError::DuplicateValueError(keys.join("."))),
+            };
+        }
+        if mapping.contains_key(*last) {
+            return Err(HelmError::DuplicateValueError(keys.join(".")));
+        }
+
+        let placeholder = format!("__flagship_value_{}__", self.placeholders.len());
+        mapping.insert(
+            Value::from(*last),
+            std::mem::replace(value, placeholder.clone().into()),
+        );
+
+        let path: Vec<String> = keys.iter().map(|key| format!("{key:?}")).collect();
+        let mut expression = format!("index .Values {}", path.join(" "));
+        if quote {
+            expression.push_str(" | quote");
+        }
+        self.placeholders.push((placeholder, expression));
+        Ok(())
+    }
+}
+
+/// `name` without its `<namespace>-` prefix, which every recipe object carries.
+pub(crate) fn short_name(name: &str, namespace: &str) -> String {
+    name.strip_prefix(namespace)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::*;
+
+    /// Evaluates the `index .Values ...` expressions the exporter writes, as Helm would.
+    fn render(template: &str, values: &Value) -> String {
+        let mut rendered = String::default();
+        let mut rest = template;
+        while let Some((before, after)) = rest.split_once("{{ ") {
+            let (expression, after) = after.split_once(" }}").unwrap();
+            let (path, quote) = match expression.strip_suffix(" | quote") {
+                Some(path) => (path, true),
+                None => (expression, false),
+            };
+            let value = path
+                .trim_start_matches("index .Values ")
+                .split(' ')
+                .fold(values, |value, key| &value[key.trim_matches('"')]);
+            let value = match value {
+                Value::String(s) if quote => format!("{s:?}"),
+                Value::Number(n) => n.to_string(),
+                other => panic!("unexpected value {other:?}"),
+            };
+            rendered.push_str(before);
+            rendered.push_str(&value);
+            rest = after;
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+
+    #[test]
+    fn reproduces_stack_with_default_values() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Ephemeral("pr-1".into()))
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+        let chart = stack.as_helm_chart(&HelmExporter::new("example").with_app_version("1.0"))?;
+
+        let values: Value = serde_yaml::from_str(chart.values())?;
+        assert_eq!(values["web"]["replicas"], 2);
+        assert_eq!(values["web"]["image"], "nginx:1.27");
+        assert_eq!(values["db"]["env"]["POSTGRES_PASSWORD"], "postgres");
+        assert_eq!(values["db"]["storage"], "5Gi");
+
+        let statefulset = &chart.files()[Path::new("templates/statefulset-example-pr-1-db.yaml")];
+        assert!(statefulset.contains("image: {{ index .Values \"db\" \"image\" | quote }}\n"));
+
+        let rendered: Vec<Value> = chart
+            .files()
+            .iter()
+            .filter(|(path, _)| path.starts_with("templates"))
+            .map(|(_, template)| serde_yaml::from_str(&render(template, &values)))
+            .collect::<Result<_, _>>()?;
+        let mut expected = stack.as_k8s()?;
+        expected.sort_by_key(|object| serde_yaml::to_string(object).unwrap());
+        let mut rendered = rendered;
+        rendered.sort_by_key(|object| serde_yaml::to_string(object).unwrap());
+        assert_eq!(rendered, expected);
+
+        Ok(())
+    }
+}
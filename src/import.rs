@@ -0,0 +1,947 @@
+use std::fmt;
+
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use crate::{
+    check::{selects, sequence, str_at, ObjectRef},
+    diff::{self, ObjectChange},
+    graph::pod_labels,
+    helm::short_name,
+    prelude::*,
+    recipes::*,
+};
+
+const POD_SPEC: &str = "spec.template.spec";
+
+/// Container fields the microservice recipe renders; a container setting anything else isn't
+/// mapped to it.
+const MICROSERVICE_CONTAINER_FIELDS: &[&str] =
+    &["name", "image", "imagePullPolicy", "ports", "env"];
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("SerdeYamlError: {0}")]
+    SerdeYamlError(#[from] serde_yaml::Error),
+    #[error("SerdeJsonError: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("StackError: {0}")]
+    StackError(#[from] StackError),
+}
+
+pub type ImportResult<T> = Result<T, ImportError>;
+
+/// Something about an object that the import couldn't carry over as a recipe, or that the
+/// recipe renders differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportWarning {
+    object: ObjectRef,
+    message: String,
+}
+
+/// A stack recovered from existing manifests, see [`import`].
+#[derive(Debug, Clone)]
+pub struct Import {
+    namespace: String,
+    create_namespace: bool,
+    resources: Vec<Imported>,
+    warnings: Vec<ImportWarning>,
+}
+
+#[derive(Debug, Clone)]
+enum Imported {
+    Recipe(Resource),
+    /// A kind flagship models, with builder code that reproduces it.
+    Typed {
+        object: Value,
+        code: String,
+    },
+    Raw(Value),
+}
+
+impl ImportWarning {
+    fn new<M: Into<String>>(object: &Value, message: M) -> Self {
+        ImportWarning {
+            object: ObjectRef::of(object),
+            message: message.into(),
+        }
+    }
+
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.object, self.message)
+    }
+}
+
+impl Import {
+    pub fn stack(&self) -> Stack {
+        self.resources
+            .iter()
+            .fold(
+                Stack::builder(
+                    self.namespace.clone(),
+                    self.create_namespace,
+                    Environment::Production,
+                ),
+                |stack, imported| {
+                    stack.with_resource(match imported {
+                        Imported::Recipe(resource) => resource.clone(),
+                        Imported::Typed { object, .. } | Imported::Raw(object) => {
+                            Resource::Object(object.clone())
+                        }
+                    })
+                },
+            )
+            .build()
+    }
+
+    pub fn warnings(&self) -> &[ImportWarning] {
+        &self.warnings
+    }
+
+    /// The stack as a YAML definition, as [`Stack::from_yaml`] reads it.
+    pub fn to_definition(&self) -> ImportResult<String> {
+        Ok(serde_yaml::to_string(&self.stack())?)
+    }
+
+    /// A Rust function building the stack: recipes for the mapped resources, typed builders for
+    /// objects of the kinds flagship models and inline JSON for the rest.
+    pub fn to_rust(&self) -> ImportResult<String> {
+        let mut code = format!(
+            "use flagship::{{prelude::*, recipes::*}};\n\n\
+             pub fn stack() -> Result<Stack, Box<dyn std::error::Error>> {{\n    \
+             Ok(Stack::builder({:?}, {}, Environment::Production)\n",
+            self.namespace, self.create_namespace
+        );
+        for imported in &self.resources {
+            let resource = match imported {
+                Imported::Recipe(resource) => recipe_code(resource)?,
+                Imported::Typed { code, .. } => format!("Resource::object(&{code})?"),
+                Imported::Raw(object) => format!(
+                    "Resource::Object(serde_yaml::from_str({})?)",
+                    raw_string(&serde_json::to_string(object)?)
+                ),
+            };
+            code.push_str(&format!(
+                "        .with_resource({})\n",
+                indent(&resource, 2)
+            ));
+        }
+        code.push_str("        .build())\n}\n");
+        Ok(code)
+    }
+}
+
+/// Recovers a stack from existing objects. Workloads that match a recipe are mapped to it
+/// together with the Services selecting them: a Postgres or RabbitMQ StatefulSet with a
+/// ClusterIP Service, an nginx Deployment with a LoadBalancer Service, and a single-container
+/// Deployment with at most a LoadBalancer Service as a microservice. Every field the recipe
+/// renders differently is reported as a warning.
+///
+/// The stack is named after the namespace of the objects. Everything else, including objects in
+/// other namespaces, is kept as a raw object with a warning.
+pub fn import(objects: &[Value]) -> ImportResult<Import> {
+    let namespace = namespace(objects);
+    let in_namespace = |object: &Value| str_at(&object["metadata"]["namespace"]) == namespace;
+    let mut used = vec![false; objects.len()];
+    let mut reasons = vec![None; objects.len()];
+    let mut import = Import {
+        namespace: namespace.clone(),
+        create_namespace: false,
+        resources: Vec::default(),
+        warnings: Vec::default(),
+    };
+
+    for (i, object) in objects.iter().enumerate() {
+        if str_at(&object["kind"]) == "Namespace"
+            && str_at(&object["metadata"]["name"]) == namespace
+            && object.as_mapping().is_some_and(|o| o.len() == 3)
+            && object["metadata"]
+                .as_mapping()
+                .is_some_and(|m| m.len() == 1)
+        {
+            used[i] = true;
+            import.create_namespace = true;
+        }
+    }
+
+    let workloads: Vec<usize> = (0..objects.len())
+        .filter(|&i| {
+            matches!(str_at(&objects[i]["kind"]), "Deployment" | "StatefulSet")
+                && in_namespace(&objects[i])
+        })
+        .collect();
+    let selected = |service: &Value, workload: &Value| {
+        str_at(&service["kind"]) == "Service"
+            && in_namespace(service)
+            && service["spec"]["selector"]
+                .as_mapping()
+                .is_some_and(|s| !s.is_empty() && selects(s, pod_labels(workload, POD_SPEC)))
+    };
+
+    let mut recipes = Vec::default();
+    for &w in &workloads {
+        let workload = &objects[w];
+        let services: Vec<usize> = (0..objects.len())
+            .filter(|&s| !used[s] && selected(&objects[s], workload))
+            .collect();
+        if let Some(&shared) = services.iter().find(|&&s| {
+            workloads
+                .iter()
+                .any(|&other| other != w && selected(&objects[s], &objects[other]))
+        }) {
+            reasons[w] = Some(format!(
+                "Service {} also selects other workloads",
+                str_at(&objects[shared]["metadata"]["name"])
+            ));
+            continue;
+        }
+
+        let services: Vec<&Value> = services.iter().map(|&s| &objects[s]).collect();
+        match recognize(&namespace, workload, &services) {
+            Ok(resource) => {
+                used[w] = true;
+                for (s, object) in objects.iter().enumerate() {
+                    if services
+                        .iter()
+                        .any(|service| std::ptr::eq(*service, object))
+                    {
+                        used[s] = true;
+                    }
+                }
+                let mut originals = vec![workload];
+                originals.extend(services);
+                import
+                    .warnings
+                    .extend(differences(&namespace, &resource, &originals)?);
+                recipes.push((w, resource));
+            }
+            Err(reason) => reasons[w] = Some(reason),
+        }
+    }
+
+    for (i, object) in objects.iter().enumerate() {
+        if let Some((_, resource)) = recipes.iter().find(|(w, _)| *w == i) {
+            import.resources.push(Imported::Recipe(resource.clone()));
+            continue;
+        }
+        if used[i] {
+            continue;
+        }
+
+        let reason = match reasons[i].take() {
+            Some(reason) => reason,
+            None if !in_namespace(object) && str_at(&object["kind"]) != "Namespace" => {
+                format!("not in namespace {namespace}")
+            }
+            None => format!("no recipe renders a {}", str_at(&object["kind"])),
+        };
+        import.warnings.push(ImportWarning::new(
+            object,
+            format!("kept as a raw object: {reason}"),
+        ));
+        import.resources.push(match typed(object) {
+            Some(code) => Imported::Typed {
+                object: object.clone(),
+                code,
+            },
+            None => Imported::Raw(object.clone()),
+        });
+    }
+
+    Ok(import)
+}
+
+/// The namespace of the first Namespace object, or else the most common one.
+fn namespace(objects: &[Value]) -> String {
+    if let Some(namespace) = objects
+        .iter()
+        .find(|o| str_at(&o["kind"]) == "Namespace")
+        .map(|o| str_at(&o["metadata"]["name"]))
+    {
+        return namespace.into();
+    }
+
+    let mut counts: Vec<(&str, usize)> = Vec::default();
+    for object in objects {
+        let namespace = str_at(&object["metadata"]["namespace"]);
+        match counts.iter_mut().find(|(n, _)| *n == namespace) {
+            Some((_, count)) => *count += 1,
+            None if !namespace.is_empty() => counts.push((namespace, 1)),
+            None => {}
+        }
+    }
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map_or("default", |(namespace, _)| namespace)
+        .into()
+}
+
+fn recognize(namespace: &str, workload: &Value, services: &[&Value]) -> Result<Resource, String> {
+    let kind = str_at(&workload["kind"]);
+    let pod = &workload["spec"]["template"]["spec"];
+    let [container] = sequence(&pod["containers"]) else {
+        return Err(format!(
+            "has {} containers, recipes run one",
+            sequence(&pod["containers"]).len()
+        ));
+    };
+    let image = str_at(&container["image"]).to_string();
+    let repository = ImageRef::parse(&image)
+        .map_err(|error| error.to_string())?
+        .repository()
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let replicas = match &workload["spec"]["replicas"] {
+        Value::Null => 1,
+        replicas => replicas
+            .as_u64()
+            .and_then(|r| u32::try_from(r).ok())
+            .ok_or("spec.replicas is not a replica count")?,
+    };
+
+    match (kind, repository.as_str()) {
+        ("StatefulSet", "postgres" | "postgresql") => {
+            single_service(services, "ClusterIP", "postgresql")?;
+            Ok(Resource::PosgreSQL(PostgreSQL::new(image)))
+        }
+        ("StatefulSet", "rabbitmq") => {
+            single_service(services, "ClusterIP", "rabbitmq")?;
+            Ok(Resource::RabbitMQ(RabbitMQ::new(image)))
+        }
+        ("StatefulSet", _) => Err(format!("no recipe runs {image} as a StatefulSet")),
+        ("Deployment", "nginx") => {
+            single_service(services, "LoadBalancer", "nginx")?;
+            Ok(Resource::Nginx(Nginx::new(image, replicas)))
+        }
+        _ => microservice(namespace, workload, container, services, image, replicas),
+    }
+}
+
+fn single_service(services: &[&Value], service_type: &str, recipe: &str) -> Result<(), String> {
+    let [service] = services else {
+        return Err(format!(
+            "the {recipe} recipe renders one Service, {} select the workload",
+            services.len()
+        ));
+    };
+    let actual = match str_at(&service["spec"]["type"]) {
+        "" => "ClusterIP",
+        actual => actual,
+    };
+    if actual != service_type {
+        return Err(format!(
+            "Service {} is {actual}, the {recipe} recipe renders a {service_type} Service",
+            str_at(&service["metadata"]["name"])
+        ));
+    }
+    Ok(())
+}
+
+fn microservice(
+    namespace: &str,
+    workload: &Value,
+    container: &Value,
+    services: &[&Value],
+    image: String,
+    replicas: u32,
+) -> Result<Resource, String> {
+    if let Some(key) = unknown_key(&workload["spec"], &["replicas", "selector", "template"])
+        .or_else(|| unknown_key(&workload["spec"]["template"]["spec"], &["containers"]))
+    {
+        return Err(format!(
+            "sets {key}, which the microservice recipe doesn't render"
+        ));
+    }
+    if let Some(key) = unknown_key(container, MICROSERVICE_CONTAINER_FIELDS) {
+        return Err(format!(
+            "container sets {key}, which the microservice recipe doesn't render"
+        ));
+    }
+
+    let env = sequence(&container["env"])
+        .iter()
+        .map(|env| serde_yaml::from_value(env.clone()))
+        .collect::<Result<Vec<EnvironmentVariable>, _>>()
+        .map_err(|error| format!("container env: {error}"))?;
+
+    let container_ports = sequence(&container["ports"]);
+    if container_ports.is_empty() {
+        if !services.is_empty() {
+            return Err("is selected by a Service but exposes no ports".into());
+        }
+    } else {
+        single_service(services, "LoadBalancer", "microservice")?;
+    }
+    let service_ports = services
+        .first()
+        .map_or(&[][..], |service| sequence(&service["spec"]["ports"]));
+    if service_ports.len() != container_ports.len() {
+        return Err("the microservice recipe publishes every container port".into());
+    }
+
+    let mut ports = Vec::default();
+    for port in container_ports {
+        let number = port["containerPort"].as_u64().unwrap_or_default();
+        let service_port = service_ports
+            .iter()
+            .find(|p| p["port"].as_u64() == Some(number))
+            .filter(|p| matches!(&p["targetPort"], Value::Null) || p["targetPort"] == number)
+            .ok_or_else(|| {
+                format!("the microservice recipe publishes container port {number} as is")
+            })?;
+        let port = u16::try_from(number).map_err(|_| format!("invalid port {number}"))?;
+        let name = service_port["name"].as_str().map(String::from);
+        ports.push(match str_at(&service_port["protocol"]) {
+            "UDP" => MicroservicePort::UDP { port, name },
+            _ => MicroservicePort::TCP { port, name },
+        });
+    }
+
+    let labels = &workload["metadata"]["labels"];
+    let role = match str_at(&labels["role"]) {
+        "" => short_name(str_at(&workload["metadata"]["name"]), namespace),
+        role => role.into(),
+    };
+    let version = match str_at(&labels["version"]) {
+        "" => ImageRef::parse(&image)
+            .ok()
+            .and_then(|image| image.tag().map(String::from))
+            .unwrap_or_else(|| "latest".into()),
+        version => version.into(),
+    };
+
+    Ok(Resource::Microservice(Microservice::new(
+        image, version, replicas, role, env, ports,
+    )))
+}
+
+fn unknown_key<'a>(value: &'a Value, known: &[&str]) -> Option<&'a str> {
+    value
+        .as_mapping()?
+        .keys()
+        .filter_map(Value::as_str)
+        .find(|key| !known.contains(key))
+}
+
+/// Renders `resource` and reports how it differs from the objects it replaces, matched by
+/// kind. Images are compared in normalized form, so `nginx` and `docker.io/library/nginx` agree.
+fn differences(
+    namespace: &str,
+    resource: &Resource,
+    originals: &[&Value],
+) -> ImportResult<Vec<ImportWarning>> {
+    let rendered = Stack::builder(namespace, false, Environment::Production)
+        .with_resource(resource.clone())
+        .build()
+        .as_k8s()?;
+
+    let mut warnings = Vec::default();
+    for new in &rendered {
+        let Some(original) = originals.iter().find(|o| o["kind"] == new["kind"]) else {
+            continue;
+        };
+        let mut old = (*original).clone();
+        let name = str_at(&new["metadata"]["name"]);
+        if str_at(&old["metadata"]["name"]) != name {
+            warnings.push(ImportWarning::new(original, format!("renamed to {name}")));
+            old["metadata"]["name"] = name.into();
+        }
+        if let Some(containers) = old
+            .get_mut("spec")
+            .and_then(|spec| spec.get_mut("template"))
+            .and_then(|template| template.get_mut("spec"))
+            .and_then(|pod| pod.get_mut("containers"))
+            .and_then(Value::as_sequence_mut)
+        {
+            for container in containers {
+                if let Ok(image) = ImageRef::parse(str_at(&container["image"])) {
+                    container["image"] = image.to_string().into();
+                }
+            }
+        }
+
+        for object in diff::diff(&[old], std::slice::from_ref(new)).objects() {
+            if let ObjectChange::Modified(changes) = object.change() {
+                warnings.extend(changes.iter().map(|change| {
+                    ImportWarning::new(original, format!("the recipe changes {change}"))
+                }));
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+/// Builder code for a Deployment or Service, if the builders reproduce it: the same calls are
+/// made to build the object, which must not differ from the original.
+fn typed(object: &Value) -> Option<String> {
+    let (code, built) = match str_at(&object["kind"]) {
+        "Deployment" => deployment(object)?,
+        "Service" => service(object)?,
+        _ => return None,
+    };
+    diff::diff(std::slice::from_ref(object), &[built])
+        .is_empty()
+        .then_some(code)
+}
+
+fn deployment(object: &Value) -> Option<(String, Value)> {
+    let spec = &object["spec"];
+    let template = &spec["template"];
+    let pod = &template["spec"];
+    if object["apiVersion"] != "apps/v1"
+        || unknown_key(object, &["apiVersion", "kind", "metadata", "spec"]).is_some()
+        || unknown_key(spec, &["replicas", "selector", "template"]).is_some()
+        || unknown_key(&spec["selector"], &["matchLabels"]).is_some()
+        || unknown_key(template, &["metadata", "spec"]).is_some()
+        || unknown_key(&template["metadata"], &["namespace", "labels"]).is_some()
+        || unknown_key(pod, &["containers", "terminationGracePeriodSeconds"]).is_some()
+    {
+        return None;
+    }
+
+    let (metadata_code, metadata) = metadata(&object["metadata"])?;
+    let replicas = match &spec["replicas"] {
+        Value::Null => 1,
+        replicas => u32::try_from(replicas.as_u64()?).ok()?,
+    };
+
+    let mut selector_code = String::from("Selector::builder()");
+    let mut selector = Selector::builder();
+    for (key, value) in strings(&spec["selector"]["matchLabels"])? {
+        selector_code.push_str(&format!("\n    .with_match_label({key:?}, {value:?})"));
+        selector = selector.with_match_label(key, value);
+    }
+    selector_code.push_str("\n    .build()?");
+
+    let template_namespace = str_at(&template["metadata"]["namespace"]);
+    let mut template_metadata_code =
+        format!("DeploymentTemplateMetadata::builder({template_namespace:?})");
+    let mut template_metadata = DeploymentTemplateMetadata::builder(template_namespace);
+    for (key, value) in strings(&template["metadata"]["labels"])? {
+        template_metadata_code.push_str(&format!("\n    .with_label({key:?}, {value:?})"));
+        template_metadata = template_metadata.with_label(key, value);
+    }
+    template_metadata_code.push_str("\n    .build()?");
+
+    let mut pod_code = String::from("DeploymentTemplateSpec::builder()");
+    let mut pod_spec = DeploymentTemplateSpec::builder();
+    for container in sequence(&pod["containers"]) {
+        let (code, built) = self::container(container)?;
+        pod_code.push_str(&format!("\n    .with_container({})", indent(&code, 1)));
+        pod_spec = pod_spec.with_container(built);
+    }
+    if let Value::Number(seconds) = &pod["terminationGracePeriodSeconds"] {
+        let seconds = u32::try_from(seconds.as_u64()?).ok()?;
+        pod_code.push_str(&format!(
+            "\n    .with_termination_grace_period_seconds({seconds})"
+        ));
+        pod_spec = pod_spec.with_termination_grace_period_seconds(seconds);
+    }
+    pod_code.push_str("\n    .build()?");
+
+    let code = format!(
+        "Deployment::new(\n    {},\n    DeploymentSpec::new(\n        {replicas},\n        {},\n        \
+         DeploymentTemplate::new(\n            {},\n            {},\n        ),\n    ),\n)",
+        indent(&metadata_code, 1),
+        indent(&selector_code, 2),
+        indent(&template_metadata_code, 3),
+        indent(&pod_code, 3),
+    );
+    let built = Deployment::new(
+        metadata,
+        DeploymentSpec::new(
+            replicas,
+            selector.build().ok()?,
+            DeploymentTemplate::new(template_metadata.build().ok()?, pod_spec.build().ok()?),
+        ),
+    );
+    Some((code, serde_yaml::to_value(built).ok()?))
+}
+
+fn service(object: &Value) -> Option<(String, Value)> {
+    let spec = &object["spec"];
+    if object["apiVersion"] != "v1"
+        || unknown_key(object, &["apiVersion", "kind", "metadata", "spec"]).is_some()
+        || unknown_key(spec, &["type", "selector", "ports"]).is_some()
+    {
+        return None;
+    }
+
+    let (metadata_code, metadata) = metadata(&object["metadata"])?;
+    let (service_type, code_type) = match str_at(&spec["type"]) {
+        "" | "ClusterIP" => (ServiceType::ClusterIP, "ClusterIP"),
+        "LoadBalancer" => (ServiceType::LoadBalancer, "LoadBalancer"),
+        "NodePort" => (ServiceType::NodePort, "NodePort"),
+        _ => return None,
+    };
+    let mut spec_code = format!("ServiceSpec::builder(ServiceType::{code_type})");
+    let mut built_spec = ServiceSpec::builder(service_type);
+    for (key, value) in strings(&spec["selector"])? {
+        spec_code.push_str(&format!("\n    .with_selector({key:?}, {value:?})"));
+        built_spec = built_spec.with_selector(key, value);
+    }
+    for port in sequence(&spec["ports"]) {
+        if unknown_key(port, &["port", "targetPort", "name"]).is_some() {
+            return None;
+        }
+        let number = u16::try_from(port["port"].as_u64()?).ok()?;
+        let name = port["name"].as_str();
+        let name_code = match name {
+            Some(name) => format!("Some({name:?})"),
+            None => "None::<&str>".into(),
+        };
+        let (target, target_code) = match &port["targetPort"] {
+            Value::Null => (IntOrString::from(number), number.to_string()),
+            Value::Number(target) => {
+                let target = u16::try_from(target.as_u64()?).ok()?;
+                (IntOrString::from(target), target.to_string())
+            }
+            Value::String(target) => (IntOrString::from(target.as_str()), format!("{target:?}")),
+            _ => return None,
+        };
+        spec_code.push_str(&format!(
+            "\n    .with_port({number}, {target_code}, {name_code})"
+        ));
+        built_spec = built_spec.with_port(number, target, name);
+    }
+    spec_code.push_str("\n    .build()?");
+
+    let code = format!(
+        "Service::new(\n    {},\n    {},\n)",
+        indent(&metadata_code, 1),
+        indent(&spec_code, 1)
+    );
+    let built = Service::new(metadata, built_spec.build().ok()?);
+    Some((code, serde_yaml::to_value(built).ok()?))
+}
+
+fn metadata(metadata: &Value) -> Option<(String, Metadata)> {
+    if unknown_key(metadata, &["name", "namespace", "labels", "annotations"]).is_some() {
+        return None;
+    }
+    let name = str_at(&metadata["name"]);
+    let namespace = str_at(&metadata["namespace"]);
+    let mut code = format!("Metadata::builder({name:?}, {namespace:?})");
+    let mut builder = Metadata::builder(name, namespace);
+    for (key, value) in strings(&metadata["labels"])? {
+        code.push_str(&format!("\n    .with_label({key:?}, {value:?})"));
+        builder = builder.with_label(key, value);
+    }
+    for (key, value) in strings(&metadata["annotations"])? {
+        code.push_str(&format!("\n    .with_annotation({key:?}, {value:?})"));
+        builder = builder.with_annotation(key, value);
+    }
+    code.push_str("\n    .build()?");
+    Some((code, builder.build().ok()?))
+}
+
+fn container(container: &Value) -> Option<(String, Container)> {
+    let known = [
+        "name",
+        "image",
+        "imagePullPolicy",
+        "command",
+        "args",
+        "workingDir",
+        "ports",
+        "env",
+        "resources",
+    ];
+    if unknown_key(container, &known).is_some() {
+        return None;
+    }
+
+    let command = string_list(&container["command"])?;
+    let mut code = format!(
+        "Container::builder({:?}, {:?}, vec!{command:?})",
+        str_at(&container["image"]),
+        str_at(&container["name"])
+    );
+    let mut builder = Container::builder(
+        str_at(&container["image"]),
+        str_at(&container["name"]),
+        command,
+    );
+
+    if let Some(policy) = container["imagePullPolicy"].as_str() {
+        builder = builder.with_image_pull_policy(serde_yaml::from_value(policy.into()).ok()?);
+        code.push_str(&format!(
+            "\n    .with_image_pull_policy(ImagePullPolicy::{policy})"
+        ));
+    }
+    let args = string_list(&container["args"])?;
+    if !args.is_empty() {
+        code.push_str(&format!("\n    .with_args(vec!{args:?})"));
+        builder = builder.with_args(args);
+    }
+    if let Some(dir) = container["workingDir"].as_str() {
+        code.push_str(&format!("\n    .with_working_dir({dir:?})"));
+        builder = builder.with_working_dir(dir);
+    }
+    for port in sequence(&container["ports"]) {
+        if unknown_key(port, &["containerPort", "protocol"]).is_some() {
+            return None;
+        }
+        let number = u16::try_from(port["containerPort"].as_u64()?).ok()?;
+        let (port, constructor) = match str_at(&port["protocol"]) {
+            "" | "TCP" => (ContainerPort::tcp(number), "tcp"),
+            "UDP" => (ContainerPort::udp(number), "udp"),
+            _ => return None,
+        };
+        code.push_str(&format!(
+            "\n    .with_port(ContainerPort::{constructor}({number}))"
+        ));
+        builder = builder.with_port(port);
+    }
+    for env in sequence(&container["env"]) {
+        let variable: EnvironmentVariable = serde_yaml::from_value(env.clone()).ok()?;
+        match (env["value"].as_str(), env.as_mapping().map(Mapping::len)) {
+            (Some(value), Some(2)) => code.push_str(&format!(
+                "\n    .with_env(EnvironmentVariable::value({:?}, {value:?}))",
+                str_at(&env["name"])
+            )),
+            _ => code.push_str(&format!(
+                "\n    .with_env(serde_yaml::from_str({})?)",
+                raw_string(&serde_json::to_string(env).ok()?)
+            )),
+        }
+        builder = builder.with_env(variable);
+    }
+    if !container["resources"].is_null() {
+        code.push_str(&format!(
+            "\n    .with_resources(serde_yaml::from_str({})?)",
+            raw_string(&serde_json::to_string(&container["resources"]).ok()?)
+        ));
+        builder = builder.with_resources(container["resources"].clone());
+    }
+    code.push_str("\n    .build()?");
+    Some((code, builder.build().ok()?))
+}
+
+fn recipe_code(resource: &Resource) -> ImportResult<String> {
+    let value = serde_yaml::to_value(resource)?;
+    let image = str_at(&value["image"]);
+    Ok(match resource {
+        Resource::PosgreSQL(_) => format!("Resource::PosgreSQL(PostgreSQL::new({image:?}.into()))"),
+        Resource::RabbitMQ(_) => format!("Resource::RabbitMQ(RabbitMQ::new({image:?}.into()))"),
+        Resource::Nginx(_) => format!(
+            "Resource::Nginx(Nginx::new({image:?}.into(), {}))",
+            value["replicas"].as_u64().unwrap_or(1)
+        ),
+        Resource::Microservice(_) => {
+            let mut env = String::new();
+            for variable in sequence(&value["env"]) {
+                match (
+                    variable["value"].as_str(),
+                    variable.as_mapping().map(Mapping::len),
+                ) {
+                    (Some(v), Some(2)) => env.push_str(&format!(
+                        "\n        EnvironmentVariable::value({:?}, {v:?}),",
+                        str_at(&variable["name"])
+                    )),
+                    _ => env.push_str(&format!(
+                        "\n        serde_yaml::from_str({})?,",
+                        raw_string(&serde_json::to_string(variable).unwrap_or_default())
+                    )),
+                }
+            }
+            let mut ports = String::new();
+            for port in sequence(&value["ports"]) {
+                let protocol = match str_at(&port["protocol"]) {
+                    "udp" => "UDP",
+                    _ => "TCP",
+                };
+                let name = match port["name"].as_str() {
+                    Some(name) => format!("Some({name:?}.into())"),
+                    None => "None".into(),
+                };
+                ports.push_str(&format!(
+                    "\n        MicroservicePort::{protocol} {{ port: {}, name: {name} }},",
+                    port["port"].as_u64().unwrap_or_default()
+                ));
+            }
+            format!(
+                "Resource::Microservice(Microservice::new(\n    {image:?}.into(),\n    {:?}.into(),\n    \
+                 {},\n    {:?}.into(),\n    vec![{env}{}],\n    vec![{ports}{}],\n))",
+                str_at(&value["version"]),
+                value["replicas"].as_u64().unwrap_or(1),
+                str_at(&value["role"]),
+                if env.is_empty() { "" } else { "\n    " },
+                if ports.is_empty() { "" } else { "\n    " },
+            )
+        }
+        Resource::Object(_) => unreachable!("raw objects aren't recipes"),
+    })
+}
+
+/// The string pairs of a mapping, in their original order; `None` for anything else but null.
+fn strings(value: &Value) -> Option<Vec<(&str, &str)>> {
+    match value {
+        Value::Null => Some(Vec::default()),
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, value)| Some((key.as_str()?, value.as_str()?)))
+            .collect(),
+        _ => None,
+    }
+}
+
+fn string_list(value: &Value) -> Option<Vec<&str>> {
+    match value {
+        Value::Null => Some(Vec::default()),
+        Value::Sequence(values) => values.iter().map(Value::as_str).collect(),
+        _ => None,
+    }
+}
+
+/// A raw string literal holding `s`, with enough `#`s to not end early.
+fn raw_string(s: &str) -> String {
+    let hashes = "#".repeat(
+        (1..)
+            .find(|&n| !s.contains(&format!("\"{}", "#".repeat(n))))
+            .unwrap_or(1),
+    );
+    format!("r{hashes}\"{s}\"{hashes}")
+}
+
+/// Indents every line but the first by `levels` steps of four spaces, to nest generated code.
+fn indent(code: &str, levels: usize) -> String {
+    code.replace('\n', &format!("\n{}", "    ".repeat(levels)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let objects: Vec<Value> = serde_yaml::from_str(
+            r#"
+- apiVersion: v1
+  kind: Namespace
+  metadata: {name: shop}
+- apiVersion: apps/v1
+  kind: StatefulSet
+  metadata: {name: shop-db, namespace: shop, labels: {app: shop-db}}
+  spec:
+    serviceName: shop-db-svc
+    selector: {matchLabels: {app: shop-db}}
+    template:
+      metadata: {name: shop-db, namespace: shop, labels: {app: shop-db}}
+      spec:
+        containers:
+          - name: shop-db
+            image: postgres:16
+            ports: [{containerPort: 5432}]
+- apiVersion: v1
+  kind: Service
+  metadata: {name: shop-db-svc, namespace: shop}
+  spec:
+    selector: {app: shop-db}
+    ports: [{port: 5432, targetPort: 5432, name: db}]
+- apiVersion: apps/v1
+  kind: Deployment
+  metadata: {name: shop-api, namespace: shop, labels: {app: shop-api, version: "2.1"}}
+  spec:
+    replicas: 3
+    selector: {matchLabels: {app: shop-api}}
+    template:
+      metadata: {labels: {app: shop-api}}
+      spec:
+        containers:
+          - name: shop-api
+            image: ghcr.io/shop/api:2.1
+            ports: [{containerPort: 8080}]
+            env: [{name: LOG_LEVEL, value: info}]
+- apiVersion: v1
+  kind: Service
+  metadata: {name: shop-api-svc, namespace: shop}
+  spec:
+    type: LoadBalancer
+    selector: {app: shop-api}
+    ports: [{port: 8080, targetPort: 8080, name: http}]
+- apiVersion: apps/v1
+  kind: Deployment
+  metadata: {name: worker, namespace: shop}
+  spec:
+    replicas: 2
+    selector: {matchLabels: {app: worker}}
+    template:
+      metadata: {labels: {app: worker}}
+      spec:
+        containers:
+          - name: worker
+            image: ghcr.io/shop/worker:2.1
+            imagePullPolicy: IfNotPresent
+            args: [--queue, orders]
+            resources: {requests: {cpu: 100m}}
+- apiVersion: v1
+  kind: ConfigMap
+  metadata: {name: settings, namespace: shop}
+  data: {mode: live}
+"#,
+        )?;
+
+        let import = import(&objects)?;
+        let definition = Stack::from_yaml(&import.to_definition()?)?;
+        let rendered = definition.as_k8s()?;
+        let kinds: Vec<_> = rendered.iter().map(|o| str_at(&o["kind"])).collect();
+        assert_eq!(
+            kinds,
+            [
+                "Namespace",
+                "ConfigMap",
+                "Service",
+                "Service",
+                "Deployment",
+                "Deployment",
+                "StatefulSet"
+            ]
+        );
+        let api = rendered
+            .iter()
+            .find(|o| o["metadata"]["name"] == "shop-api")
+            .unwrap();
+        assert_eq!(api["spec"]["replicas"], 3);
+        assert_eq!(api["metadata"]["labels"]["version"], "2.1");
+
+        let warnings: Vec<_> = import.warnings().iter().map(|w| w.to_string()).collect();
+        assert!(warnings.contains(
+            &"Deployment shop/shop-api: the recipe changes + metadata.labels.role: \"api\""
+                .to_string()
+        ));
+        assert!(warnings.contains(
+            &"Deployment shop/worker: kept as a raw object: container sets args, which the \
+              microservice recipe doesn't render"
+                .to_string()
+        ));
+        assert!(warnings.contains(
+            &"ConfigMap shop/settings: kept as a raw object: no recipe renders a ConfigMap"
+                .to_string()
+        ));
+
+        let rust = import.to_rust()?;
+        assert!(rust.contains("Resource::PosgreSQL(PostgreSQL::new(\"postgres:16\".into()))"));
+        assert!(
+            rust.contains("Container::builder(\"ghcr.io/shop/worker:2.1\", \"worker\", vec![])")
+        );
+        assert!(rust.contains(".with_args(vec![\"--queue\", \"orders\"])"));
+        assert!(rust.contains("Resource::Object(serde_yaml::from_str(r#\""));
+
+        Ok(())
+    }
+}
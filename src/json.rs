@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::prelude::*;
+
+/// How a set of rendered objects is laid out as JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// A single indented `v1/List`, as `kubectl get -o json` prints it.
+    #[default]
+    Pretty,
+    /// The same `v1/List` on one line.
+    Compact,
+    /// One object per line, for `jq` and line-oriented tooling.
+    Lines,
+}
+
+/// Renders `objects` as JSON. The objects can be typed flagship objects or the YAML values a
+/// stack renders; mappings with non-string keys can't be represented and are an error.
+pub fn to_string<T: Serialize>(objects: &[T], format: JsonFormat) -> serde_json::Result<String> {
+    match format {
+        JsonFormat::Pretty => serde_json::to_string_pretty(&list(objects)?),
+        JsonFormat::Compact => serde_json::to_string(&list(objects)?),
+        JsonFormat::Lines => objects.iter().try_fold(String::new(), |mut lines, object| {
+            lines.push_str(&serde_json::to_string(object)?);
+            lines.push('\n');
+            Ok(lines)
+        }),
+    }
+}
+
+/// The JSON Schema of a single flagship type, e.g. `schema::<Container>()`.
+pub fn schema<T: JsonSchema>() -> RootSchema {
+    schema_for!(T)
+}
+
+/// JSON Schemas for every kind flagship models, keyed by kind. Nested types such as
+/// `Container` appear in each schema's definitions. Fields flagship doesn't model are
+/// allowed, since manifests round-trip them unchanged.
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("Namespace", schema::<Namespace>()),
+        ("Deployment", schema::<Deployment>()),
+        ("StatefulSet", schema::<StatefulSet>()),
+        ("Service", schema::<Service>()),
+        ("Secret", schema::<Secret>()),
+    ])
+}
+
+fn list<T: Serialize>(objects: &[T]) -> serde_json::Result<serde_json::Value> {
+    Ok(json!({
+        "apiVersion": "v1",
+        "kind": "List",
+        "items": serde_json::to_value(objects)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::recipes::*;
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+
+        let lines = stack.as_json(JsonFormat::Lines)?;
+        let kinds: Vec<String> = lines
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).map(|v| v["kind"].to_string()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            kinds,
+            vec!["\"Namespace\"", "\"Service\"", "\"Deployment\""]
+        );
+
+        let list: Value = serde_json::from_str(&stack.as_json(JsonFormat::Compact)?)?;
+        assert_eq!(list["kind"], "List");
+        assert_eq!(list["items"][2]["spec"]["replicas"], 2);
+        assert_eq!(
+            stack.as_json(JsonFormat::Pretty)?,
+            serde_json::to_string_pretty(&list)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn schemas_describe_every_kind() -> anyhow::Result<()> {
+        let schemas = serde_json::to_value(schemas())?;
+        let deployment = &schemas["Deployment"];
+        assert!(deployment["properties"]["spec"].is_object());
+        assert!(deployment["definitions"]["Container"]["properties"]["image"].is_object());
+        assert_eq!(
+            deployment["definitions"]["IntOrString"]["anyOf"][1]["type"],
+            "string"
+        );
+        assert!(schemas["Service"]["additionalProperties"].is_null());
+
+        let container = serde_json::to_value(schema::<Container>())?;
+        assert_eq!(
+            container["definitions"]["ImagePullPolicy"]["enum"],
+            json!(["Always", "IfNotPresent", "Never"])
+        );
+
+        Ok(())
+    }
+}
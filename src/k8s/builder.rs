@@ -0,0 +1,68 @@
+/// Conditional chaining shared by every builder, so optional settings don't need the
+/// builder to be rebound in a `let mut`.
+pub trait Builder: Sized {
+    /// Applies `f` only when `condition` holds.
+    fn when<F: FnOnce(Self) -> Self>(self, condition: bool, f: F) -> Self {
+        if condition {
+            f(self)
+        } else {
+            self
+        }
+    }
+
+    /// Applies `f` to the value when there is one.
+    fn when_some<T, F: FnOnce(Self, T) -> Self>(self, value: Option<T>, f: F) -> Self {
+        match value {
+            Some(value) => f(self, value),
+            None => self,
+        }
+    }
+
+    /// Applies `f` once for every item, e.g. `.with_each(ports, Self::with_port)`.
+    fn with_each<T, I: IntoIterator<Item = T>, F: FnMut(Self, T) -> Self>(
+        self,
+        items: I,
+        f: F,
+    ) -> Self {
+        items.into_iter().fold(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn reuse_base_builder() -> anyhow::Result<()> {
+        let base = Container::builder("example/service:v1", "base", Vec::default())
+            .with_env(("RUST_LOG", "info").into())
+            .with_pre_stop(LifecycleHandler::sleep(5));
+
+        let debug = true;
+        let api = base
+            .clone()
+            .with_name("api")
+            .with_port(ContainerPort::tcp(8080))
+            .when(debug, |c| c.with_env(("RUST_BACKTRACE", "1").into()))
+            .build()?;
+        let worker = base
+            .with_name("worker")
+            .with_image("example/worker:v1")
+            .when_some(Some(vec!["--queue", "jobs"]), ContainerBuilder::with_args)
+            .build()?;
+
+        assert_eq!(api.name(), "api");
+        assert_eq!(api.image(), "example/service:v1");
+        assert_eq!(worker.name(), "worker");
+        assert_eq!(worker.image(), "example/worker:v1");
+
+        let api = serde_yaml::to_value(&api)?;
+        let worker = serde_yaml::to_value(&worker)?;
+        assert_eq!(api["env"].as_sequence().map(Vec::len), Some(2));
+        assert_eq!(worker["env"].as_sequence().map(Vec::len), Some(1));
+        assert_eq!(worker["args"][1].as_str(), Some("jobs"));
+        assert_eq!(worker["lifecycle"], api["lifecycle"]);
+
+        Ok(())
+    }
+}
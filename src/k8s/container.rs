@@ -1,113 +1,434 @@
-use std::cell::Cell;
-
-use derive_more::Constructor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 
-use super::{environment::EnvironmentVariable, volume::VolumeMount};
+use super::{
+    builder::Builder,
+    environment::EnvironmentVariable,
+    int_or_string::IntOrString,
+    validation::{field, Validate, ValidationErrors},
+    volume::VolumeMount,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Container {
     image: String,
-    image_pull_policy: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_pull_policy: Option<ImagePullPolicy>,
     name: String,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     command: Vec<String>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_dir: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     ports: Vec<ContainerPort>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
     resources: Option<Value>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     env: Vec<EnvironmentVariable>,
 
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     volume_mounts: Vec<VolumeMount>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lifecycle: Option<Lifecycle>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    termination_message_policy: Option<TerminationMessagePolicy>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stdin: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tty: Option<bool>,
+
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum ImagePullPolicy {
+    Always,
+    IfNotPresent,
+    Never,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum TerminationMessagePolicy {
+    File,
+    FallbackToLogsOnError,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Lifecycle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_start: Option<LifecycleHandler>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pre_stop: Option<LifecycleHandler>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LifecycleHandler {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exec: Option<ExecAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_get: Option<HttpGetAction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sleep: Option<SleepAction>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecAction {
+    command: Vec<String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpGetAction {
+    path: String,
+    port: IntOrString,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheme: Option<UriScheme>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum UriScheme {
+    HTTP,
+    HTTPS,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepAction {
+    seconds: u32,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Constructor, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ContainerPort {
     container_port: u16,
-    protocol: ContainerPortProtocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocol: Option<ContainerPortProtocol>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ContainerPortProtocol {
     TCP,
     UDP,
+    SCTP,
+}
+
+impl Container {
+    pub fn builder<S: Into<String>>(image: S, name: S, command: Vec<S>) -> ContainerBuilder {
+        ContainerBuilder {
+            container: Container {
+                image: image.into(),
+                image_pull_policy: Some(ImagePullPolicy::IfNotPresent),
+                name: name.into(),
+                command: command.into_iter().map(S::into).collect(),
+                args: Vec::default(),
+                working_dir: None,
+                ports: Vec::default(),
+                resources: None,
+                env: Vec::default(),
+                volume_mounts: Vec::default(),
+                lifecycle: None,
+                termination_message_policy: None,
+                stdin: None,
+                tty: None,
+                unknown_fields: Mapping::default(),
+            },
+        }
+    }
 }
 
 impl Container {
-    pub fn builder<S: Into<String>>(image: S, name: S, command: Vec<S>) -> Cell<Self> {
-        Cell::new(Container {
-            image: image.into(),
-            image_pull_policy: "IfNotPresent".into(),
-            name: name.into(),
-            command: command.into_iter().map(S::into).collect(),
-            ports: Vec::default(),
-            resources: None,
-            env: Vec::default(),
-            volume_mounts: Vec::default(),
-        })
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+}
+
+impl Validate for Container {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_dns1123_label(&field(path, "name"), &self.name);
+        if self.image.trim().is_empty() {
+            errors.push(field(path, "image"), "must not be empty");
+        }
+        errors.validate_each(&field(path, "ports"), &self.ports);
+        errors.validate_each(&field(path, "env"), &self.env);
+        errors.validate_each(&field(path, "volumeMounts"), &self.volume_mounts);
+        if let Some(lifecycle) = &self.lifecycle {
+            lifecycle.validate_at(&field(path, "lifecycle"), errors);
+        }
+    }
+}
+
+impl Validate for ContainerPort {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_port(&field(path, "containerPort"), self.container_port);
+    }
+}
+
+impl Validate for Lifecycle {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if let Some(handler) = &self.post_start {
+            handler.validate_at(&field(path, "postStart"), errors);
+        }
+        if let Some(handler) = &self.pre_stop {
+            handler.validate_at(&field(path, "preStop"), errors);
+        }
+    }
+}
+
+impl Validate for LifecycleHandler {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let handlers = [
+            self.exec.is_some(),
+            self.http_get.is_some(),
+            self.sleep.is_some(),
+        ];
+        if handlers.iter().filter(|set| **set).count() != 1 {
+            errors.push(path, "must specify exactly one of exec, httpGet or sleep");
+        }
+        if let Some(exec) = &self.exec {
+            errors.check_not_empty(&field(&field(path, "exec"), "command"), &exec.command);
+        }
+        if let Some(http_get) = &self.http_get {
+            errors.check_target_port(&field(&field(path, "httpGet"), "port"), &http_get.port);
+        }
+    }
+}
+
+impl LifecycleHandler {
+    pub fn exec<S: Into<String>>(command: Vec<S>) -> Self {
+        LifecycleHandler {
+            exec: Some(ExecAction::new(command.into_iter().map(S::into).collect())),
+            ..Default::default()
+        }
+    }
+
+    pub fn http_get<S: Into<String>, P: Into<IntOrString>>(path: S, port: P) -> Self {
+        LifecycleHandler::http_get_action(HttpGetAction::new(path, port))
+    }
+
+    pub fn http_get_action(action: HttpGetAction) -> Self {
+        LifecycleHandler {
+            http_get: Some(action),
+            ..Default::default()
+        }
+    }
+
+    pub fn sleep(seconds: u32) -> Self {
+        LifecycleHandler {
+            sleep: Some(SleepAction::new(seconds)),
+            ..Default::default()
+        }
+    }
+}
+
+impl ExecAction {
+    pub fn new(command: Vec<String>) -> Self {
+        ExecAction {
+            command,
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl SleepAction {
+    pub fn new(seconds: u32) -> Self {
+        SleepAction {
+            seconds,
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl HttpGetAction {
+    pub fn new<S: Into<String>, P: Into<IntOrString>>(path: S, port: P) -> Self {
+        HttpGetAction {
+            path: path.into(),
+            port: port.into(),
+            host: None,
+            scheme: None,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn with_host<S: Into<String>>(mut self, host: S) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn with_scheme(mut self, scheme: UriScheme) -> Self {
+        self.scheme = Some(scheme);
+        self
     }
 }
 
-pub trait ContainerBuilder {
-    fn with_port(self, port: ContainerPort) -> Self;
-    fn with_resources(self, resources: Value) -> Self;
-    fn with_env(self, env: EnvironmentVariable) -> Self;
-    fn with_volume_mount(self, volume_mount: VolumeMount) -> Self;
-    fn build(self) -> Container;
+#[derive(Debug, Clone)]
+pub struct ContainerBuilder {
+    container: Container,
 }
 
-impl ContainerBuilder for Cell<Container> {
-    fn with_port(self, port: ContainerPort) -> Self {
-        let mut container = self.into_inner();
-        container.ports.push(port);
-        Cell::new(container)
+impl ContainerBuilder {
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.container.name = name.into();
+        self
+    }
+
+    pub fn with_image<S: Into<String>>(mut self, image: S) -> Self {
+        self.container.image = image.into();
+        self
+    }
+
+    pub fn with_command<S: Into<String>>(mut self, command: Vec<S>) -> Self {
+        self.container.command = command.into_iter().map(S::into).collect();
+        self
+    }
+
+    pub fn with_image_pull_policy(mut self, image_pull_policy: ImagePullPolicy) -> Self {
+        self.container.image_pull_policy = Some(image_pull_policy);
+        self
+    }
+
+    pub fn with_args<S: Into<String>>(mut self, args: Vec<S>) -> Self {
+        self.container.args = args.into_iter().map(S::into).collect();
+        self
+    }
+
+    pub fn with_working_dir<S: Into<String>>(mut self, working_dir: S) -> Self {
+        self.container.working_dir = Some(working_dir.into());
+        self
+    }
+
+    pub fn with_port(mut self, port: ContainerPort) -> Self {
+        self.container.ports.push(port);
+        self
+    }
+
+    pub fn with_resources(mut self, resources: Value) -> Self {
+        self.container.resources = Some(resources);
+        self
+    }
+
+    pub fn with_env(mut self, env: EnvironmentVariable) -> Self {
+        self.container.env.push(env);
+        self
+    }
+
+    pub fn with_volume_mount(mut self, volume_mount: VolumeMount) -> Self {
+        self.container.volume_mounts.push(volume_mount);
+        self
+    }
+
+    pub fn with_post_start(mut self, handler: LifecycleHandler) -> Self {
+        self.container
+            .lifecycle
+            .get_or_insert_with(Lifecycle::default)
+            .post_start = Some(handler);
+        self
+    }
+
+    pub fn with_pre_stop(mut self, handler: LifecycleHandler) -> Self {
+        self.container
+            .lifecycle
+            .get_or_insert_with(Lifecycle::default)
+            .pre_stop = Some(handler);
+        self
+    }
+
+    pub fn with_termination_message_policy(mut self, policy: TerminationMessagePolicy) -> Self {
+        self.container.termination_message_policy = Some(policy);
+        self
     }
 
-    fn with_resources(self, resources: Value) -> Self {
-        let mut container = self.into_inner();
-        container.resources = Some(resources);
-        Cell::new(container)
+    pub fn with_stdin(mut self, stdin: bool) -> Self {
+        self.container.stdin = Some(stdin);
+        self
     }
 
-    fn with_env(self, env: EnvironmentVariable) -> Self {
-        let mut container = self.into_inner();
-        container.env.push(env);
-        Cell::new(container)
+    pub fn with_tty(mut self, tty: bool) -> Self {
+        self.container.tty = Some(tty);
+        self
     }
 
-    fn with_volume_mount(self, volume_mount: VolumeMount) -> Self {
-        let mut container = self.into_inner();
-        container.volume_mounts.push(volume_mount);
-        Cell::new(container)
+    pub fn build(self) -> Result<Container, ValidationErrors> {
+        self.container.validate()?;
+        Ok(self.container)
     }
 
-    fn build(self) -> Container {
-        self.into_inner()
+    pub fn build_unchecked(self) -> Container {
+        self.container
     }
 }
 
+impl Builder for ContainerBuilder {}
+
 impl ContainerPort {
+    pub fn new(container_port: u16, protocol: ContainerPortProtocol) -> Self {
+        ContainerPort {
+            container_port,
+            protocol: Some(protocol),
+            unknown_fields: Mapping::default(),
+        }
+    }
+
     pub fn tcp(port: u16) -> Self {
         ContainerPort {
             container_port: port,
-            protocol: ContainerPortProtocol::TCP,
+            protocol: Some(ContainerPortProtocol::TCP),
+            unknown_fields: Mapping::default(),
         }
     }
 
     pub fn udp(port: u16) -> Self {
         ContainerPort {
             container_port: port,
-            protocol: ContainerPortProtocol::UDP,
+            protocol: Some(ContainerPortProtocol::UDP),
+            unknown_fields: Mapping::default(),
         }
     }
 }
@@ -0,0 +1,156 @@
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use super::{
+    metadata::Metadata,
+    validation::{field, Validate, ValidationErrors},
+};
+
+/// An instance of a custom resource, e.g. a cert-manager `Certificate` or a KEDA
+/// `ScaledObject`. The spec and status are caller-defined types; the status defaults to an
+/// untyped value so objects read back from a cluster keep whatever the controller reported.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomResource<Spec, Status = Value> {
+    api_version: String,
+    kind: String,
+    metadata: Metadata,
+    spec: Spec,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+impl<Spec, Status> CustomResource<Spec, Status> {
+    /// Creates a custom resource of `group/version` and `kind`. An empty group produces a
+    /// core-style `apiVersion` of just `version`.
+    pub fn new<S: Into<String>>(
+        group: S,
+        version: S,
+        kind: S,
+        metadata: Metadata,
+        spec: Spec,
+    ) -> Self {
+        let group = group.into();
+        let version = version.into();
+        let api_version = if group.is_empty() {
+            version
+        } else {
+            format!("{group}/{version}")
+        };
+
+        CustomResource {
+            api_version,
+            kind: kind.into(),
+            metadata,
+            spec,
+            status: None,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn api_version(&self) -> &str {
+        &self.api_version
+    }
+
+    pub fn group(&self) -> &str {
+        self.api_version
+            .rsplit_once('/')
+            .map(|(group, _)| group)
+            .unwrap_or_default()
+    }
+
+    pub fn version(&self) -> &str {
+        self.api_version
+            .rsplit_once('/')
+            .map(|(_, version)| version)
+            .unwrap_or(&self.api_version)
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn spec(&self) -> &Spec {
+        &self.spec
+    }
+
+    pub fn status(&self) -> Option<&Status> {
+        self.status.as_ref()
+    }
+}
+
+impl<Spec, Status> Validate for CustomResource<Spec, Status> {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if self.version().is_empty() {
+            errors.push(field(path, "apiVersion"), "must include a version");
+        }
+        if self.kind.is_empty() {
+            errors.push(field(path, "kind"), "must not be empty");
+        }
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+    }
+}
+
+impl<Spec: DeserializeOwned, Status: DeserializeOwned> CustomResource<Spec, Status> {
+    /// Reads a custom resource back from an untyped object, such as a
+    /// [`Manifest::Unsupported`](crate::manifest::Manifest::Unsupported) value.
+    pub fn from_value(value: Value) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{manifest::Manifest, prelude::*};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct CertificateSpec {
+        secret_name: String,
+        dns_names: Vec<String>,
+    }
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let certificate = CustomResource::<_>::new(
+            "cert-manager.io",
+            "v1",
+            "Certificate",
+            Metadata::builder("example-tls", "example").build()?,
+            CertificateSpec {
+                secret_name: "example-tls".into(),
+                dns_names: vec!["example.com".into()],
+            },
+        );
+
+        let yaml = serde_yaml::to_string(&certificate)?;
+        println!("{yaml}");
+        assert!(yaml.starts_with("apiVersion: cert-manager.io/v1\nkind: Certificate\n"));
+
+        let value = match Manifest::parse_all(&yaml)?.pop() {
+            Some(Manifest::Unsupported(value)) => value,
+            other => panic!("unexpected manifest: {other:?}"),
+        };
+        let parsed = CustomResource::<CertificateSpec>::from_value(value)?;
+        assert_eq!(parsed.group(), "cert-manager.io");
+        assert_eq!(parsed.version(), "v1");
+        assert_eq!(parsed.spec(), certificate.spec());
+
+        Ok(())
+    }
+}
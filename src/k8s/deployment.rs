@@ -1,61 +1,89 @@
-use std::{cell::Cell, collections::HashMap};
+use std::collections::HashMap;
 
-use derive_more::Constructor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 
-use super::{container::Container, metadata::Metadata, selector::Selector, volume::Volume};
+use super::{
+    builder::Builder,
+    container::Container,
+    metadata::{LocalObjectReference, Metadata},
+    selector::Selector,
+    validation::{field, Validate, ValidationErrors},
+    volume::Volume,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Deployment {
     api_version: String,
     kind: String,
     metadata: Metadata,
     spec: DeploymentSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Constructor, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DeploymentSpec {
-    replicas: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replicas: Option<u32>,
     selector: Selector,
     template: DeploymentTemplate,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Constructor, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DeploymentTemplate {
     metadata: DeploymentTemplateMetadata,
     spec: DeploymentTemplateSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DeploymentTemplateMetadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     namespace: String,
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     labels: HashMap<String, String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DeploymentTemplateSpec {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     containers: Vec<Container>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     volumes: Vec<Volume>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    image_pull_secrets: Vec<LocalObjectReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    termination_grace_period_seconds: Option<u32>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
 /* --- TRAITS --- */
-pub trait DeploymentTemplateMetadataBuilder {
-    fn with_label<S: Into<String>>(self, key: S, value: S) -> Self;
-    fn build(self) -> DeploymentTemplateMetadata;
+#[derive(Debug, Clone)]
+pub struct DeploymentTemplateMetadataBuilder {
+    metadata: DeploymentTemplateMetadata,
 }
 
-pub trait DeploymentTemplateSpecBuilder {
-    fn with_container(self, container: Container) -> Self;
-    fn with_volume(self, volume: Volume) -> Self;
-    fn build(self) -> DeploymentTemplateSpec;
+#[derive(Debug, Clone)]
+pub struct DeploymentTemplateSpecBuilder {
+    spec: DeploymentTemplateSpec,
 }
 
 /* --- IMPLS --- */
@@ -66,55 +94,158 @@ impl Deployment {
             kind: "Deployment".into(),
             metadata,
             spec,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    /// Like [`Deployment::new`], but validates the whole object, so every violation is reported
+    /// at once with its full path, e.g. `spec.template.spec.containers[0].name`. Build the parts
+    /// with `build_unchecked` to leave all validation to this.
+    pub fn try_new(metadata: Metadata, spec: DeploymentSpec) -> Result<Self, ValidationErrors> {
+        let deployment = Deployment::new(metadata, spec);
+        deployment.validate()?;
+        Ok(deployment)
+    }
+}
+
+impl DeploymentSpec {
+    pub fn new(replicas: u32, selector: Selector, template: DeploymentTemplate) -> Self {
+        DeploymentSpec {
+            replicas: Some(replicas),
+            selector,
+            template,
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl DeploymentTemplate {
+    pub fn new(metadata: DeploymentTemplateMetadata, spec: DeploymentTemplateSpec) -> Self {
+        DeploymentTemplate {
+            metadata,
+            spec,
+            unknown_fields: Mapping::default(),
         }
     }
 }
 
 impl DeploymentTemplateMetadata {
-    pub fn builder<S: Into<String>>(namepace: S) -> Cell<Self> {
-        Cell::new(DeploymentTemplateMetadata {
-            namespace: namepace.into(),
-            labels: HashMap::default(),
-        })
+    pub fn builder<S: Into<String>>(namepace: S) -> DeploymentTemplateMetadataBuilder {
+        DeploymentTemplateMetadataBuilder {
+            metadata: DeploymentTemplateMetadata {
+                namespace: namepace.into(),
+                labels: HashMap::default(),
+                unknown_fields: Mapping::default(),
+            },
+        }
     }
 }
 
-impl DeploymentTemplateMetadataBuilder for Cell<DeploymentTemplateMetadata> {
-    fn with_label<S: Into<String>>(self, key: S, value: S) -> Self {
-        let mut metadata = self.into_inner();
-        metadata.labels.insert(key.into(), value.into());
-        Cell::new(metadata)
+impl DeploymentTemplateMetadataBuilder {
+    pub fn with_label<S: Into<String>>(mut self, key: S, value: S) -> Self {
+        self.metadata.labels.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> Result<DeploymentTemplateMetadata, ValidationErrors> {
+        self.metadata.validate()?;
+        Ok(self.metadata)
     }
 
-    fn build(self) -> DeploymentTemplateMetadata {
-        self.into_inner()
+    pub fn build_unchecked(self) -> DeploymentTemplateMetadata {
+        self.metadata
     }
 }
 
+impl Builder for DeploymentTemplateMetadataBuilder {}
+
 impl DeploymentTemplateSpec {
-    pub fn builder() -> Cell<Self> {
-        Cell::new(DeploymentTemplateSpec {
-            containers: Vec::default(),
-            volumes: Vec::default(),
-        })
+    pub fn builder() -> DeploymentTemplateSpecBuilder {
+        DeploymentTemplateSpecBuilder {
+            spec: DeploymentTemplateSpec {
+                containers: Vec::default(),
+                volumes: Vec::default(),
+                image_pull_secrets: Vec::default(),
+                termination_grace_period_seconds: None,
+                unknown_fields: Mapping::default(),
+            },
+        }
+    }
+}
+
+impl DeploymentTemplateSpecBuilder {
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.spec.containers.push(container);
+        self
+    }
+
+    pub fn with_volume(mut self, volume: Volume) -> Self {
+        self.spec.volumes.push(volume);
+        self
+    }
+
+    pub fn with_image_pull_secret<S: Into<String>>(mut self, name: S) -> Self {
+        self.spec.image_pull_secrets.push(LocalObjectReference::new(name));
+        self
+    }
+
+    pub fn with_termination_grace_period_seconds(mut self, seconds: u32) -> Self {
+        self.spec.termination_grace_period_seconds = Some(seconds);
+        self
+    }
+
+    pub fn build(self) -> Result<DeploymentTemplateSpec, ValidationErrors> {
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+
+    pub fn build_unchecked(self) -> DeploymentTemplateSpec {
+        self.spec
+    }
+}
+
+impl Builder for DeploymentTemplateSpecBuilder {}
+
+impl Validate for Deployment {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        self.spec.validate_at(&field(path, "spec"), errors);
     }
 }
 
-impl DeploymentTemplateSpecBuilder for Cell<DeploymentTemplateSpec> {
-    fn with_container(self, container: Container) -> Self {
-        let mut spec = self.into_inner();
-        spec.containers.push(container);
-        Cell::new(spec)
+impl Validate for DeploymentSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.selector.validate_at(&field(path, "selector"), errors);
+        self.template.validate_at(&field(path, "template"), errors);
     }
+}
 
-    fn with_volume(self, volume: Volume) -> Self {
-        let mut spec = self.into_inner();
-        spec.volumes.push(volume);
-        Cell::new(spec)
+impl Validate for DeploymentTemplate {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        self.spec.validate_at(&field(path, "spec"), errors);
     }
+}
 
-    fn build(self) -> DeploymentTemplateSpec {
-        self.into_inner()
+impl Validate for DeploymentTemplateMetadata {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if !self.namespace.is_empty() {
+            errors.check_dns1123_label(&field(path, "namespace"), &self.namespace);
+        }
+        errors.check_labels(&field(path, "labels"), &self.labels);
+    }
+}
+
+impl Validate for DeploymentTemplateSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let containers = field(path, "containers");
+        errors.check_not_empty(&containers, &self.containers);
+        errors.validate_each(&containers, &self.containers);
+        errors.check_unique_names(&containers, self.containers.iter().map(Container::name));
+
+        let volumes = field(path, "volumes");
+        errors.validate_each(&volumes, &self.volumes);
+        errors.check_unique_names(&volumes, self.volumes.iter().map(Volume::name));
     }
 }
 
@@ -128,16 +259,16 @@ mod tests {
         let deployment = Deployment::new(
             Metadata::builder("example-deployment", "example")
                 .with_label("example-label", "example-label-value")
-                .build(),
+                .build()?,
             DeploymentSpec::new(
                 3,
                 Selector::builder()
                     .with_match_label("app", "example-api-svc")
-                    .build(),
+                    .build()?,
                 DeploymentTemplate::new(
                     DeploymentTemplateMetadata::builder("example")
                         .with_label("app", "example-api")
-                        .build(),
+                        .build()?,
                     DeploymentTemplateSpec::builder()
                         .with_container(
                             Container::builder(
@@ -145,13 +276,19 @@ mod tests {
                                 "example-app",
                                 vec!["/usr/bin/app"],
                             )
+                            .with_args(vec!["--listen", "0.0.0.0:8080"])
+                            .with_image_pull_policy(ImagePullPolicy::Always)
                             .with_env(("EXAMPLE_VAR", "example value").into())
                             .with_port(ContainerPort::tcp(8080))
                             .with_volume_mount(VolumeMount::new("logs".into(), "/var/logs".into()))
-                            .build(),
+                            .with_pre_stop(LifecycleHandler::sleep(5))
+                            .with_post_start(LifecycleHandler::http_get("/warmup", 8080))
+                            .build()?,
                         )
                         .with_volume(Volume::empty_dir("logs".into()))
-                        .build(),
+                        .with_image_pull_secret("registry-credentials")
+                        .with_termination_grace_period_seconds(60)
+                        .build()?,
                 ),
             ),
         );
@@ -159,6 +296,13 @@ mod tests {
         let yaml = serde_yaml::to_string(&deployment)?;
         println!("{yaml}");
 
+        assert!(yaml.contains("imagePullPolicy: Always"));
+        assert!(yaml.contains("preStop:\n"));
+        assert!(yaml.contains("seconds: 5"));
+        assert!(yaml.contains("httpGet:\n"));
+        assert!(yaml.contains("imagePullSecrets:\n"));
+        assert!(yaml.contains("terminationGracePeriodSeconds: 60"));
+
         Ok(())
     }
 }
@@ -1,13 +1,28 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+use super::{
+    int_or_string::IntOrString,
+    validation::{field, Validate, ValidationErrors},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EnvironmentVariable {
     name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     value: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "serde_yaml::with::singleton_map"
+    )]
+    #[schemars(with = "Option<ValueFrom>")]
     value_from: Option<ValueFrom>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
 impl<Name: Into<String>, Value: Into<String>> From<(Name, Value)> for EnvironmentVariable {
@@ -22,6 +37,7 @@ impl EnvironmentVariable {
             name: name.into(),
             value: Some(value.into()),
             value_from: None,   
+            unknown_fields: Mapping::default(),
         }
     }
 
@@ -30,16 +46,138 @@ impl EnvironmentVariable {
             name: name.into(),
             value: None,
             value_from: Some(value_from),
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl Validate for EnvironmentVariable {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if self.name.is_empty() || self.name.contains('=') {
+            errors.push(
+                field(path, "name"),
+                "must be non-empty and must not contain '='",
+            );
+        }
+        if self.value.is_some() && self.value_from.is_some() {
+            errors.push(path, "may not specify both value and valueFrom");
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum ValueFrom {
-    FieldRef { field_path: String },
-    SecretKeyRef { key: String, name: String, optional: bool },
-    ResourceFieldRef { resource: String, container_name: String, divisor: String }
+    FieldRef(FieldRef),
+    SecretKeyRef(KeySelector),
+    ConfigMapKeyRef(KeySelector),
+    ResourceFieldRef(ResourceFieldRef),
+    /// Any other source, such as `fileKeyRef`, kept as written.
+    #[serde(untagged)]
+    #[schemars(with = "std::collections::HashMap<String, serde_json::Value>")]
+    Other(Mapping),
+}
+
+/// A field of the pod, e.g. `metadata.name` or `status.podIP`.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldRef {
+    field_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_version: Option<String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+/// A key of a Secret or ConfigMap.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeySelector {
+    key: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    optional: Option<bool>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+/// A resource limit or request of a container, e.g. `limits.memory`.
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceFieldRef {
+    resource: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    divisor: Option<IntOrString>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+impl ValueFrom {
+    pub fn field_ref<S: Into<String>>(field_path: S) -> Self {
+        ValueFrom::FieldRef(FieldRef {
+            field_path: field_path.into(),
+            api_version: None,
+            unknown_fields: Mapping::default(),
+        })
+    }
+
+    pub fn secret_key_ref<N: Into<String>, K: Into<String>>(name: N, key: K) -> Self {
+        ValueFrom::SecretKeyRef(KeySelector::new(name, key))
+    }
+
+    pub fn config_map_key_ref<N: Into<String>, K: Into<String>>(name: N, key: K) -> Self {
+        ValueFrom::ConfigMapKeyRef(KeySelector::new(name, key))
+    }
+
+    pub fn resource_field_ref<R: Into<String>>(resource: R) -> Self {
+        ValueFrom::ResourceFieldRef(ResourceFieldRef {
+            resource: resource.into(),
+            container_name: None,
+            divisor: None,
+            unknown_fields: Mapping::default(),
+        })
+    }
+}
+
+impl KeySelector {
+    pub fn new<N: Into<String>, K: Into<String>>(name: N, key: K) -> Self {
+        KeySelector {
+            key: key.into(),
+            name: name.into(),
+            optional: None,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn with_optional(mut self, optional: bool) -> Self {
+        self.optional = Some(optional);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl ResourceFieldRef {
+    pub fn with_container_name<S: Into<String>>(mut self, container_name: S) -> Self {
+        self.container_name = Some(container_name.into());
+        self
+    }
+
+    pub fn with_divisor<D: Into<IntOrString>>(mut self, divisor: D) -> Self {
+        self.divisor = Some(divisor.into());
+        self
+    }
 }
 
 mod tests {
@@ -47,12 +185,10 @@ mod tests {
     fn test_serialize() {
         let env = vec![
             super::EnvironmentVariable::value("TEST", "test"),
-            super::EnvironmentVariable::value_from("FIELD", super::ValueFrom::FieldRef {field_path: "metadata.name".into()}),
-            super::EnvironmentVariable::value_from("SECRET", super::ValueFrom::SecretKeyRef{
-                key: "key".into(),
-                name: "secret".into(),
-                optional: false
-            }),
+            super::EnvironmentVariable::value_from("FIELD", super::ValueFrom::field_ref("metadata.name")),
+            super::EnvironmentVariable::value_from("SECRET", super::ValueFrom::SecretKeyRef(
+                super::KeySelector::new("secret", "key").with_optional(false)
+            )),
         ];
 
         let yaml = serde_yaml::to_string(&env).unwrap();
@@ -0,0 +1,349 @@
+use std::{fmt, str::FromStr};
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_LEGACY: &str = "index.docker.io";
+const DOCKER_HUB_LIBRARY: &str = "library";
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_TAG_LENGTH: usize = 128;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ImageRefError {
+    #[error("image reference is empty")]
+    Empty,
+    #[error("image reference {0:?} is longer than {MAX_NAME_LENGTH} characters")]
+    TooLong(String),
+    #[error("invalid registry {0:?}")]
+    InvalidRegistry(String),
+    #[error("invalid repository {0:?}")]
+    InvalidRepository(String),
+    #[error("invalid tag {0:?}")]
+    InvalidTag(String),
+    #[error("invalid digest {0:?}")]
+    InvalidDigest(String),
+}
+
+/// A parsed container image reference of the form `registry/repository[:tag][@digest]`.
+///
+/// Docker Hub shorthands are normalized on parse, so `nginx` becomes
+/// `docker.io/library/nginx` and `index.docker.io/foo/bar` becomes `docker.io/foo/bar`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImageRef {
+    registry: String,
+    repository: String,
+    tag: Option<String>,
+    digest: Option<String>,
+}
+
+impl ImageRef {
+    pub fn parse(reference: &str) -> Result<Self, ImageRefError> {
+        if reference.is_empty() {
+            return Err(ImageRefError::Empty);
+        }
+
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => (name, Some(parse_digest(digest)?)),
+            None => (reference, None),
+        };
+
+        let last_component_start = name.rfind('/').map(|i| i + 1).unwrap_or(0);
+        let (name, tag) = match name[last_component_start..].rfind(':') {
+            Some(i) => {
+                let i = last_component_start + i;
+                (&name[..i], Some(parse_tag(&name[i + 1..])?))
+            }
+            None => (name, None),
+        };
+
+        if name.len() > MAX_NAME_LENGTH {
+            return Err(ImageRefError::TooLong(reference.into()));
+        }
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((first, rest)) if is_registry(first) => (first, rest),
+            _ => (DOCKER_HUB, name),
+        };
+
+        if !is_valid_registry(registry) {
+            return Err(ImageRefError::InvalidRegistry(registry.into()));
+        }
+
+        if repository.is_empty() || !repository.split('/').all(is_valid_path_component) {
+            return Err(ImageRefError::InvalidRepository(repository.into()));
+        }
+
+        let registry = match registry {
+            DOCKER_HUB_LEGACY => DOCKER_HUB,
+            registry => registry,
+        };
+
+        let repository = if registry == DOCKER_HUB && !repository.contains('/') {
+            format!("{DOCKER_HUB_LIBRARY}/{repository}")
+        } else {
+            repository.to_string()
+        };
+
+        Ok(ImageRef {
+            registry: registry.into(),
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// True when the reference either carries a digest or a tag other than `latest`.
+    pub fn is_pinned(&self) -> bool {
+        self.digest.is_some() || self.tag.as_deref().is_some_and(|tag| tag != "latest")
+    }
+
+    /// Moves the image to another registry. The target may carry a path prefix, as pull-through
+    /// mirrors usually do, e.g. `mirror.example.com/dockerhub`.
+    pub fn with_registry(self, registry: &str) -> Self {
+        let (registry, repository) = match registry.split_once('/') {
+            Some((registry, prefix)) => (
+                registry.to_string(),
+                format!("{}/{}", prefix.trim_end_matches('/'), self.repository),
+            ),
+            None => (registry.to_string(), self.repository),
+        };
+
+        ImageRef {
+            registry,
+            repository,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for ImageRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ImageRef {
+    type Err = ImageRefError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImageRef::parse(s)
+    }
+}
+
+impl From<ImageRef> for String {
+    fn from(image: ImageRef) -> Self {
+        image.to_string()
+    }
+}
+
+impl Serialize for ImageRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let reference = String::deserialize(deserializer)?;
+        ImageRef::parse(&reference).map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for ImageRef {
+    fn schema_name() -> String {
+        "ImageRef".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+fn is_registry(component: &str) -> bool {
+    component.contains('.') || component.contains(':') || component == "localhost"
+}
+
+fn is_valid_registry(registry: &str) -> bool {
+    let (host, port) = match registry.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (registry, None),
+    };
+
+    let valid_port = port.is_none_or(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+    let valid_host = !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        });
+
+    valid_host && valid_port
+}
+
+/// Path components are lowercase alphanumerics joined by `.`, `_`, `__` or runs of `-`.
+fn is_valid_path_component(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    if bytes.is_empty() || !is_lower_alnum(bytes[0]) || !is_lower_alnum(bytes[bytes.len() - 1]) {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_lower_alnum(bytes[i]) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < bytes.len() && !is_lower_alnum(bytes[i]) {
+            i += 1;
+        }
+
+        match &component[start..i] {
+            "." | "_" | "__" => {}
+            separator if separator.bytes().all(|b| b == b'-') => {}
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+fn is_lower_alnum(b: u8) -> bool {
+    b.is_ascii_lowercase() || b.is_ascii_digit()
+}
+
+fn parse_tag(tag: &str) -> Result<String, ImageRefError> {
+    let mut chars = tag.chars();
+    let valid = tag.len() <= MAX_TAG_LENGTH
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-');
+
+    if valid {
+        Ok(tag.into())
+    } else {
+        Err(ImageRefError::InvalidTag(tag.into()))
+    }
+}
+
+fn parse_digest(digest: &str) -> Result<String, ImageRefError> {
+    let invalid = || ImageRefError::InvalidDigest(digest.into());
+    let (algorithm, encoded) = digest.split_once(':').ok_or_else(invalid)?;
+
+    let valid_algorithm = !algorithm.is_empty()
+        && algorithm
+            .split(['+', '.', '_', '-'])
+            .all(|part| !part.is_empty() && part.bytes().all(is_lower_alnum));
+
+    let valid_encoded = match algorithm {
+        "sha256" => encoded.len() == 64 && encoded.bytes().all(|b| b.is_ascii_hexdigit()),
+        "sha512" => encoded.len() == 128 && encoded.bytes().all(|b| b.is_ascii_hexdigit()),
+        _ => {
+            encoded.len() >= 32
+                && encoded
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'=' || b == b'_' || b == b'-')
+        }
+    };
+
+    if valid_algorithm && valid_encoded {
+        Ok(digest.into())
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:4a3cf1e3e5bbd1f8fd2b6fdb8f8a9e1e2c5e0b2f8a4c1d6e7f8091a2b3c4d5e6";
+
+    #[test]
+    fn normalizes_docker_hub_shorthands() -> anyhow::Result<()> {
+        let image = ImageRef::parse("nginx")?;
+        assert_eq!(image.registry(), "docker.io");
+        assert_eq!(image.repository(), "library/nginx");
+        assert_eq!(image.tag(), None);
+        assert_eq!(image.to_string(), "docker.io/library/nginx");
+
+        let image = ImageRef::parse("index.docker.io/bitnami/rabbitmq:3.13")?;
+        assert_eq!(image.to_string(), "docker.io/bitnami/rabbitmq:3.13");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_registry_tag_and_digest() -> anyhow::Result<()> {
+        let image = ImageRef::parse(&format!("localhost:5000/team/api:v1.2.3@{DIGEST}"))?;
+        assert_eq!(image.registry(), "localhost:5000");
+        assert_eq!(image.repository(), "team/api");
+        assert_eq!(image.tag(), Some("v1.2.3"));
+        assert_eq!(image.digest(), Some(DIGEST));
+        assert!(image.is_pinned());
+
+        assert!(!ImageRef::parse("ghcr.io/org/app:latest")?.is_pinned());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_references() {
+        assert_eq!(ImageRef::parse(""), Err(ImageRefError::Empty));
+        assert!(matches!(
+            ImageRef::parse("Nginx"),
+            Err(ImageRefError::InvalidRepository(_))
+        ));
+        assert!(matches!(
+            ImageRef::parse("nginx:"),
+            Err(ImageRefError::InvalidTag(_))
+        ));
+        assert!(matches!(
+            ImageRef::parse("nginx@sha256:abc"),
+            Err(ImageRefError::InvalidDigest(_))
+        ));
+        assert!(matches!(
+            ImageRef::parse("bad_host.io/app"),
+            Err(ImageRefError::InvalidRegistry(_))
+        ));
+    }
+
+    #[test]
+    fn rewrites_registry() -> anyhow::Result<()> {
+        let image = ImageRef::parse("postgres:16")?.with_registry("mirror.example.com/hub");
+        assert_eq!(
+            image.to_string(),
+            "mirror.example.com/hub/library/postgres:16"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum IntOrString {
+    Int(i32),
+    String(String),
+}
+
+impl From<u16> for IntOrString {
+    fn from(value: u16) -> Self {
+        IntOrString::Int(value.into())
+    }
+}
+
+impl From<i32> for IntOrString {
+    fn from(value: i32) -> Self {
+        IntOrString::Int(value)
+    }
+}
+
+impl From<&str> for IntOrString {
+    fn from(value: &str) -> Self {
+        IntOrString::String(value.into())
+    }
+}
+
+impl From<String> for IntOrString {
+    fn from(value: String) -> Self {
+        IntOrString::String(value)
+    }
+}
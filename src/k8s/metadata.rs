@@ -1,39 +1,184 @@
-use std::{cell::Cell, collections::HashMap};
+use std::collections::HashMap;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::{
+    builder::Builder,
+    validation::{field, Validate, ValidationErrors},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Metadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    generate_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     namespace: String,
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     labels: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    annotations: HashMap<String, String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-impl Metadata {
-    pub fn builder<S: Into<String>>(name: S, namespace: S) -> Cell<Metadata> {
-        Cell::new(Metadata {
+/// Metadata of a pod template, where the name is optional.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    namespace: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    labels: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    annotations: HashMap<String, String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalObjectReference {
+    name: String,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+impl LocalObjectReference {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        LocalObjectReference {
             name: name.into(),
-            namespace: namespace.into(),
-            labels: HashMap::default(),
-        })
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl Metadata {
+    pub fn builder<S: Into<String>>(name: S, namespace: S) -> MetadataBuilder {
+        MetadataBuilder {
+            metadata: Metadata {
+                name: name.into(),
+                generate_name: String::new(),
+                namespace: namespace.into(),
+                labels: HashMap::default(),
+                annotations: HashMap::default(),
+                unknown_fields: Mapping::default(),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn generate_name(&self) -> &str {
+        &self.generate_name
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    pub fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+}
+
+impl TemplateMetadata {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    pub fn annotations(&self) -> &HashMap<String, String> {
+        &self.annotations
+    }
+}
+
+impl From<Metadata> for TemplateMetadata {
+    fn from(metadata: Metadata) -> Self {
+        TemplateMetadata {
+            name: Some(metadata.name).filter(|name| !name.is_empty()),
+            namespace: metadata.namespace,
+            labels: metadata.labels,
+            annotations: metadata.annotations,
+            unknown_fields: metadata.unknown_fields,
+        }
+    }
+}
+
+impl Validate for Metadata {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if !self.name.is_empty() {
+            errors.check_dns1123_subdomain(&field(path, "name"), &self.name);
+        } else if self.generate_name.is_empty() {
+            errors.push(field(path, "name"), "name or generateName is required");
+        }
+        if !self.namespace.is_empty() {
+            errors.check_dns1123_label(&field(path, "namespace"), &self.namespace);
+        }
+        errors.check_labels(&field(path, "labels"), &self.labels);
+        errors.check_annotations(&field(path, "annotations"), &self.annotations);
     }
 }
 
-pub trait MetadataBuilder {
-    fn with_label<S: Into<String>>(self, key: S, value: S) -> Self;
-    fn build(self) -> Metadata;
+#[derive(Debug, Clone)]
+pub struct MetadataBuilder {
+    metadata: Metadata,
 }
 
-impl MetadataBuilder for Cell<Metadata> {
-    fn with_label<S: Into<String>>(self, key: S, value: S) -> Self {
-        let mut m = self.into_inner();
-        m.labels.insert(key.into(), value.into());
-        Cell::new(m)
+impl MetadataBuilder {
+    pub fn with_label<S: Into<String>>(mut self, key: S, value: S) -> Self {
+        self.metadata.labels.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_annotation<S: Into<String>>(mut self, key: S, value: S) -> Self {
+        self.metadata.annotations.insert(key.into(), value.into());
+        self
     }
 
-    fn build(self) -> Metadata {
-        self.into_inner()
+    pub fn build(self) -> Result<Metadata, ValidationErrors> {
+        self.metadata.validate()?;
+        Ok(self.metadata)
+    }
+
+    pub fn build_unchecked(self) -> Metadata {
+        self.metadata
+    }
+}
+
+impl Builder for MetadataBuilder {}
+
+impl Validate for TemplateMetadata {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        if let Some(name) = &self.name {
+            errors.check_dns1123_subdomain(&field(path, "name"), name);
+        }
+        if !self.namespace.is_empty() {
+            errors.check_dns1123_label(&field(path, "namespace"), &self.namespace);
+        }
+        errors.check_labels(&field(path, "labels"), &self.labels);
+        errors.check_annotations(&field(path, "annotations"), &self.annotations);
     }
 }
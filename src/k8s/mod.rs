@@ -8,3 +8,9 @@ pub mod stateful_set;
 pub mod selector;
 pub mod service;
 pub mod namespace;
+pub mod int_or_string;
+pub mod image;
+pub mod quantity;
+pub mod custom_resource;
+pub mod validation;
+pub mod builder;
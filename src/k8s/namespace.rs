@@ -1,12 +1,19 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 
-#[derive(Debug, Serialize, Deserialize)]
+use super::validation::{field, Validate, ValidationErrors};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Namespace {
     api_version: String,
     kind: String,
+    #[schemars(with = "serde_json::Value")]
     metadata: Value,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
 impl Namespace {
@@ -15,6 +22,17 @@ impl Namespace {
             api_version: "v1".into(),
             kind: "Namespace".into(),
             metadata: Value::Mapping(vec![("name".into(), name.into())].into_iter().collect()),
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl Validate for Namespace {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let name = field(&field(path, "metadata"), "name");
+        match self.metadata["name"].as_str() {
+            Some(value) => errors.check_dns1123_label(&name, value),
+            None => errors.push(name, "must be set"),
         }
     }
 }
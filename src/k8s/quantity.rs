@@ -0,0 +1,191 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Mul},
+    str::FromStr,
+};
+
+use serde::Serialize;
+use serde_yaml::Value;
+use thiserror::Error;
+
+const BINARY_SUFFIXES: &[(&str, f64)] = &[
+    ("Ki", 1024.0),
+    ("Mi", 1048576.0),
+    ("Gi", 1073741824.0),
+    ("Ti", 1099511627776.0),
+    ("Pi", 1125899906842624.0),
+    ("Ei", 1152921504606846976.0),
+];
+
+const DECIMAL_SUFFIXES: &[(&str, f64)] = &[
+    ("n", 1e-9),
+    ("u", 1e-6),
+    ("m", 1e-3),
+    ("k", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+    ("E", 1e18),
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum QuantityError {
+    #[error("quantity is empty")]
+    Empty,
+    #[error("invalid quantity {0:?}")]
+    Invalid(String),
+}
+
+/// A Kubernetes resource quantity such as `250m`, `1.5Gi` or `1e3`, held as a plain number of
+/// base units (cores, bytes). Precise enough for estimates, not for round-tripping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize)]
+pub struct Quantity(f64);
+
+impl Quantity {
+    pub fn new(value: f64) -> Self {
+        Quantity(value)
+    }
+
+    pub fn parse(quantity: &str) -> Result<Self, QuantityError> {
+        let quantity = quantity.trim();
+        if quantity.is_empty() {
+            return Err(QuantityError::Empty);
+        }
+        let invalid = || QuantityError::Invalid(quantity.to_string());
+
+        let end = quantity
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+' || c == '-'))
+            .unwrap_or(quantity.len());
+        let (number, suffix) = quantity.split_at(end);
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+
+        let multiplier = if suffix.is_empty() {
+            1.0
+        } else if let Some((_, m)) = BINARY_SUFFIXES
+            .iter()
+            .chain(DECIMAL_SUFFIXES)
+            .find(|(s, _)| *s == suffix)
+        {
+            *m
+        } else if let Some(exponent) = suffix.strip_prefix(['e', 'E']) {
+            10f64.powi(exponent.parse().map_err(|_| invalid())?)
+        } else {
+            return Err(invalid());
+        };
+        Ok(Quantity(number * multiplier))
+    }
+
+    /// Reads a quantity from a manifest field, where it may be a string or a plain number.
+    pub fn from_value(value: &Value) -> Result<Self, QuantityError> {
+        match value {
+            Value::Null => Err(QuantityError::Empty),
+            Value::Number(number) => number
+                .as_f64()
+                .map(Quantity)
+                .ok_or_else(|| QuantityError::Invalid(number.to_string())),
+            Value::String(quantity) => Quantity::parse(quantity),
+            other => Err(QuantityError::Invalid(format!("{other:?}"))),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// Formats a CPU quantity as whole cores or millicores, e.g. `2` or `250m`.
+    pub fn to_cores(&self) -> String {
+        let millis = (self.0 * 1000.0).round();
+        if millis % 1000.0 == 0.0 {
+            format!("{}", millis / 1000.0)
+        } else {
+            format!("{millis}m")
+        }
+    }
+
+    /// Formats a byte quantity with the largest binary suffix that keeps it at least 1, e.g.
+    /// `512Mi` or `1.5Gi`.
+    pub fn to_bytes(&self) -> String {
+        match BINARY_SUFFIXES
+            .iter()
+            .rev()
+            .find(|(_, m)| self.0.abs() >= *m)
+        {
+            Some((suffix, m)) => format!("{}{suffix}", trim(self.0 / m)),
+            None => trim(self.0),
+        }
+    }
+}
+
+fn trim(value: f64) -> String {
+    let formatted = format!("{value:.2}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+impl FromStr for Quantity {
+    type Err = QuantityError;
+
+    fn from_str(quantity: &str) -> Result<Self, Self::Err> {
+        Quantity::parse(quantity)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", trim(self.0))
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: Quantity) -> Quantity {
+        Quantity(self.0 + other.0)
+    }
+}
+
+impl Mul<f64> for Quantity {
+    type Output = Quantity;
+
+    fn mul(self, factor: f64) -> Quantity {
+        Quantity(self.0 * factor)
+    }
+}
+
+impl Sum for Quantity {
+    fn sum<I: Iterator<Item = Quantity>>(iter: I) -> Quantity {
+        iter.fold(Quantity::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats() -> Result<(), QuantityError> {
+        assert_eq!(Quantity::parse("250m")?.value(), 0.25);
+        assert_eq!(Quantity::parse("2")?.to_cores(), "2");
+        assert_eq!(Quantity::parse("0.5")?.to_cores(), "500m");
+        assert_eq!(Quantity::parse("1.5Gi")?.to_bytes(), "1.5Gi");
+        assert_eq!(Quantity::parse("128Mi")?.to_bytes(), "128Mi");
+        assert_eq!(Quantity::parse("1e3")?.value(), 1000.0);
+        assert_eq!(Quantity::parse("1k")?, Quantity::new(1000.0));
+        assert_eq!(
+            (Quantity::parse("512Mi")? * 2.0 + Quantity::parse("1Gi")?).to_bytes(),
+            "2Gi"
+        );
+
+        assert_eq!(Quantity::parse(""), Err(QuantityError::Empty));
+        assert_eq!(
+            Quantity::parse("1Gb"),
+            Err(QuantityError::Invalid("1Gb".into()))
+        );
+
+        Ok(())
+    }
+}
@@ -1,52 +1,100 @@
-use std::{cell::Cell, collections::HashMap};
+use std::collections::HashMap;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_yaml::Value;
+use serde_yaml::{Mapping, Value};
 
-use super::metadata::Metadata;
+use super::{
+    builder::Builder,
+    metadata::Metadata,
+    validation::{field, sorted, Validate, ValidationErrors},
+};
 
-#[derive(Debug, Serialize, Deserialize)]
+const SECRET_KEY_MAX_LENGTH: usize = 253;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Secret {
     api_version: String,
     kind: String,
     metadata: Metadata,
-    #[serde(rename = "type")]
-    secret_type: SecretType,
-    data: HashMap<String, Value>
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    secret_type: Option<SecretType>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    data: HashMap<String, Value>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum SecretType {
-    Opaque
+    Opaque,
+    #[serde(rename = "kubernetes.io/dockerconfigjson")]
+    DockerConfigJson,
+    #[serde(rename = "kubernetes.io/tls")]
+    Tls,
+    #[serde(rename = "kubernetes.io/basic-auth")]
+    BasicAuth,
+    #[serde(rename = "kubernetes.io/ssh-auth")]
+    SshAuth,
+    #[serde(rename = "kubernetes.io/service-account-token")]
+    ServiceAccountToken,
+    /// Any other type, such as `bootstrap.kubernetes.io/token` or a custom one.
+    #[serde(untagged)]
+    Other(String),
 }
 
-pub trait SecretBuilder {
-    fn with_data<S: Into<String>>(self, key: S, value: Value) -> Self;
-    fn build(self) -> Secret;
+#[derive(Debug, Clone)]
+pub struct SecretBuilder {
+    secret: Secret,
 }
 
 impl Secret { 
-    pub fn builder(secret_type: SecretType, metadata: Metadata) -> Cell<Secret> {
-        Cell::new(Secret {
-            api_version: "v1".into(),
-            kind: "Secret".into(),
-            metadata,
-            secret_type,
-            data: HashMap::default(),
-        })
+    pub fn builder(secret_type: SecretType, metadata: Metadata) -> SecretBuilder {
+        SecretBuilder {
+            secret: Secret {
+                api_version: "v1".into(),
+                kind: "Secret".into(),
+                metadata,
+                secret_type: Some(secret_type),
+                data: HashMap::default(),
+                unknown_fields: Mapping::default(),
+            },
+        }
     }
 }
 
-impl SecretBuilder for Cell<Secret> {
-    fn with_data<S: Into<String>>(self, key: S, value: Value) -> Self {
-        let mut secret = self.into_inner();
-        secret.data.insert(key.into(), value);
-        Cell::new(secret)
+impl Validate for Secret {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        for (key, _) in sorted(&self.data) {
+            let valid = !key.is_empty()
+                && key.len() <= SECRET_KEY_MAX_LENGTH
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+            if !valid {
+                errors.push(
+                    field(&field(path, "data"), key),
+                    "keys must consist of alphanumeric characters, '-', '_' or '.'",
+                );
+            }
+        }
     }
+}
 
-    fn build(self) -> Secret {
-        self.into_inner()
+impl SecretBuilder {
+    pub fn with_data<S: Into<String>>(mut self, key: S, value: Value) -> Self {
+        self.secret.data.insert(key.into(), value);
+        self
     }
-}
\ No newline at end of file
+
+    pub fn build(self) -> Result<Secret, ValidationErrors> {
+        self.secret.validate()?;
+        Ok(self.secret)
+    }
+}
+
+impl Builder for SecretBuilder {}
@@ -1,34 +1,64 @@
-use std::{cell::Cell, collections::HashMap};
+use std::collections::HashMap;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 
-#[derive(Debug, Deserialize, Serialize)]
+use super::{
+    builder::Builder,
+    validation::{field, Validate, ValidationErrors},
+};
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Selector {
-    match_labels: HashMap<String, String>
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    match_labels: HashMap<String, String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-pub trait SelectorBuilder {
-    fn with_match_label<S: Into<String>>(self, key: S, value: S) -> Self;
-    fn build(self) -> Selector;
+#[derive(Debug, Clone)]
+pub struct SelectorBuilder {
+    selector: Selector,
 }
 
 impl Selector {
-    pub fn builder() -> Cell<Self> {
-        Cell::new(Selector {
-            match_labels: HashMap::default()
-        })
+    pub fn builder() -> SelectorBuilder {
+        SelectorBuilder {
+            selector: Selector {
+                match_labels: HashMap::default(),
+                unknown_fields: Mapping::default(),
+            },
+        }
+    }
+}
+
+impl Validate for Selector {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let match_labels = field(path, "matchLabels");
+        if self.match_labels.is_empty() && !self.unknown_fields.contains_key("matchExpressions") {
+            errors.push(&match_labels, "must contain at least one label");
+        }
+        errors.check_labels(&match_labels, &self.match_labels);
     }
 }
 
-impl SelectorBuilder for Cell<Selector> {
-    fn with_match_label<S: Into<String>>(self, key: S, value: S) -> Self {
-        let mut selector = self.into_inner();
-        selector.match_labels.insert(key.into(), value.into());
-        Cell::new(selector)
+impl SelectorBuilder {
+    pub fn with_match_label<S: Into<String>>(mut self, key: S, value: S) -> Self {
+        self.selector.match_labels.insert(key.into(), value.into());
+        self
     }
 
-    fn build(self) -> Selector {
-        self.into_inner()
+    pub fn build(self) -> Result<Selector, ValidationErrors> {
+        self.selector.validate()?;
+        Ok(self.selector)
     }
-}
\ No newline at end of file
+
+    pub fn build_unchecked(self) -> Selector {
+        self.selector
+    }
+}
+
+impl Builder for SelectorBuilder {}
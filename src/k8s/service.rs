@@ -1,41 +1,62 @@
-use derive_more::Constructor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{cell::Cell, collections::HashMap};
+use serde_yaml::Mapping;
+use std::collections::HashMap;
 
-use super::metadata::Metadata;
+use super::{
+    builder::Builder,
+    int_or_string::IntOrString,
+    metadata::Metadata,
+    validation::{field, Validate, ValidationErrors},
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Service {
     api_version: String,
     kind: String,
     metadata: Metadata,
     spec: ServiceSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceSpec {
-    #[serde(rename = "type")]
-    service_type: ServiceType,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    service_type: Option<ServiceType>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     selector: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     ports: Vec<ServicePort>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "PascalCase")]
 pub enum ServiceType {
     LoadBalancer,
     ClusterIP,
+    NodePort,
+    ExternalName,
 }
 
-#[derive(Debug, Constructor, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ServicePort {
     port: u16,
-    target_port: u16,
+    /// Defaults to `port` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target_port: Option<IntOrString>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    name: Option<String>
+    name: Option<String>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
 impl Service {
@@ -45,40 +66,124 @@ impl Service {
             kind: "Service".into(),
             metadata,
             spec,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    /// Like [`Service::new`], but validates the whole object, see
+    /// [`Deployment::try_new`](super::deployment::Deployment::try_new).
+    pub fn try_new(metadata: Metadata, spec: ServiceSpec) -> Result<Self, ValidationErrors> {
+        let service = Service::new(metadata, spec);
+        service.validate()?;
+        Ok(service)
+    }
+}
+
+impl Validate for Service {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let metadata = field(path, "metadata");
+        self.metadata.validate_at(&metadata, errors);
+        errors.check_dns1035_label(&field(&metadata, "name"), self.metadata.name());
+        self.spec.validate_at(&field(path, "spec"), errors);
+    }
+}
+
+impl Validate for ServiceSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_labels(&field(path, "selector"), &self.selector);
+
+        let ports = field(path, "ports");
+        if !matches!(self.service_type, Some(ServiceType::ExternalName)) {
+            errors.check_not_empty(&ports, &self.ports);
+        }
+        errors.validate_each(&ports, &self.ports);
+
+        if self.ports.len() > 1 {
+            for (i, port) in self.ports.iter().enumerate() {
+                if port.name.is_none() {
+                    errors.push(
+                        field(&format!("{ports}[{i}]"), "name"),
+                        "must be set when the service has more than one port",
+                    );
+                }
+            }
+            errors.check_unique_names(
+                &ports,
+                self.ports.iter().filter_map(|port| port.name.as_deref()),
+            );
+        }
+    }
+}
+
+impl Validate for ServicePort {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_port(&field(path, "port"), self.port);
+        if let Some(target_port) = &self.target_port {
+            errors.check_target_port(&field(path, "targetPort"), target_port);
+        }
+        if let Some(name) = &self.name {
+            errors.check_port_name(&field(path, "name"), name);
+        }
+    }
+}
+
+impl ServicePort {
+    pub fn new(port: u16, target_port: IntOrString, name: Option<String>) -> Self {
+        ServicePort {
+            port,
+            target_port: Some(target_port),
+            name,
+            unknown_fields: Mapping::default(),
         }
     }
 }
 
 impl ServiceSpec {
-    pub fn builder(service_type: ServiceType) -> Cell<ServiceSpec> {
-        Cell::new(ServiceSpec {
-            service_type,
-            selector: HashMap::default(),
-            ports: Vec::default(),
-        })
+    pub fn builder(service_type: ServiceType) -> ServiceSpecBuilder {
+        ServiceSpecBuilder {
+            spec: ServiceSpec {
+                service_type: Some(service_type),
+                selector: HashMap::default(),
+                ports: Vec::default(),
+                unknown_fields: Mapping::default(),
+            },
+        }
     }
 }
 
-pub trait ServiceSpecBuilder {
-    fn with_port<S: Into<String>>(self, port: u16, target_port: u16, name: Option<S>) -> Self;
-    fn with_selector<S: Into<String>>(self, name: S, value: S) -> Self;
-    fn build(self) -> ServiceSpec;
+#[derive(Debug, Clone)]
+pub struct ServiceSpecBuilder {
+    spec: ServiceSpec,
 }
 
-impl ServiceSpecBuilder for Cell<ServiceSpec> {
-    fn with_port<S: Into<String>>(self, port: u16, target_port: u16, name: Option<S>) -> Self {
-        let mut spec = self.into_inner();
-        spec.ports.push(ServicePort::new(port, target_port, name.map(S::into)));
-        Cell::new(spec)
+impl ServiceSpecBuilder {
+    pub fn with_port<S: Into<String>, T: Into<IntOrString>>(
+        mut self,
+        port: u16,
+        target_port: T,
+        name: Option<S>,
+    ) -> Self {
+        self.spec.ports.push(ServicePort::new(
+            port,
+            target_port.into(),
+            name.map(S::into),
+        ));
+        self
     }
 
-    fn build(self) -> ServiceSpec {
-        self.into_inner()
+    pub fn build(self) -> Result<ServiceSpec, ValidationErrors> {
+        self.spec.validate()?;
+        Ok(self.spec)
     }
 
-    fn with_selector<S: Into<String>>(self, name: S, value: S) -> Self {
-        let mut spec = self.into_inner();
-        spec.selector.insert(name.into(), value.into());
-        Cell::new(spec)
+    pub fn build_unchecked(self) -> ServiceSpec {
+        self.spec
+    }
+
+    pub fn with_selector<S: Into<String>>(mut self, name: S, value: S) -> Self {
+        self.spec.selector.insert(name.into(), value.into());
+        self
     }
 }
+
+impl Builder for ServiceSpecBuilder {}
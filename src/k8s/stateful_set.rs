@@ -1,40 +1,139 @@
-use std::cell::Cell;
-
 use super::{
-    container::Container, metadata::Metadata, selector::Selector, volume::VolumeClaimTemplate,
+    builder::Builder,
+    container::Container,
+    int_or_string::IntOrString,
+    metadata::{LocalObjectReference, Metadata, TemplateMetadata},
+    selector::Selector,
+    validation::{field, Validate, ValidationErrors},
+    volume::VolumeClaimTemplate,
 };
-use derive_more::Constructor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSet {
     api_version: String,
     kind: String,
     metadata: Metadata,
     spec: StatefulSetSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSetSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replicas: Option<u32>,
     service_name: String,
     selector: Selector,
     template: StatefulSetSpecTemplate,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     volume_claim_templates: Vec<VolumeClaimTemplate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pod_management_policy: Option<PodManagementPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update_strategy: Option<StatefulSetUpdateStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_ready_seconds: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ordinals: Option<StatefulSetOrdinals>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    persistent_volume_claim_retention_policy:
+        Option<StatefulSetPersistentVolumeClaimRetentionPolicy>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum PodManagementPolicy {
+    OrderedReady,
+    Parallel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSetUpdateStrategy {
+    #[serde(rename = "type")]
+    strategy_type: StatefulSetUpdateStrategyType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rolling_update: Option<RollingUpdateStatefulSetStrategy>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Constructor, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum StatefulSetUpdateStrategyType {
+    RollingUpdate,
+    OnDelete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RollingUpdateStatefulSetStrategy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partition: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_unavailable: Option<IntOrString>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSetOrdinals {
+    start: u32,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatefulSetPersistentVolumeClaimRetentionPolicy {
+    when_deleted: PersistentVolumeClaimRetentionPolicyType,
+    when_scaled: PersistentVolumeClaimRetentionPolicyType,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub enum PersistentVolumeClaimRetentionPolicyType {
+    Retain,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSetSpecTemplate {
-    metadata: Metadata,
+    metadata: TemplateMetadata,
     spec: StatefulSetSpecTemplateSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StatefulSetSpecTemplateSpec {
     containers: Vec<Container>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    image_pull_secrets: Vec<LocalObjectReference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    termination_grace_period_seconds: Option<u32>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
 impl StatefulSet {
@@ -44,8 +143,17 @@ impl StatefulSet {
             kind: "StatefulSet".into(),
             metadata,
             spec,
+            unknown_fields: Mapping::default(),
         }
     }
+
+    /// Like [`StatefulSet::new`], but validates the whole object, see
+    /// [`Deployment::try_new`](super::deployment::Deployment::try_new).
+    pub fn try_new(metadata: Metadata, spec: StatefulSetSpec) -> Result<Self, ValidationErrors> {
+        let stateful_set = StatefulSet::new(metadata, spec);
+        stateful_set.validate()?;
+        Ok(stateful_set)
+    }
 }
 
 impl StatefulSetSpec {
@@ -53,55 +161,238 @@ impl StatefulSetSpec {
         service_name: String,
         selector: Selector,
         template: StatefulSetSpecTemplate,
-    ) -> Cell<Self> {
-        Cell::new(StatefulSetSpec {
-            service_name,
-            selector,
-            template,
-            volume_claim_templates: Vec::new(),
-        })
+    ) -> StatefulSetSpecBuilder {
+        StatefulSetSpecBuilder {
+            spec: StatefulSetSpec {
+                replicas: None,
+                service_name,
+                selector,
+                template,
+                volume_claim_templates: Vec::new(),
+                pod_management_policy: None,
+                update_strategy: None,
+                min_ready_seconds: None,
+                ordinals: None,
+                persistent_volume_claim_retention_policy: None,
+                unknown_fields: Mapping::default(),
+            },
+        }
+    }
+}
+
+impl StatefulSetUpdateStrategy {
+    pub fn rolling_update(partition: Option<u32>, max_unavailable: Option<IntOrString>) -> Self {
+        StatefulSetUpdateStrategy {
+            strategy_type: StatefulSetUpdateStrategyType::RollingUpdate,
+            rolling_update: Some(RollingUpdateStatefulSetStrategy::new(
+                partition,
+                max_unavailable,
+            )),
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn on_delete() -> Self {
+        StatefulSetUpdateStrategy {
+            strategy_type: StatefulSetUpdateStrategyType::OnDelete,
+            rolling_update: None,
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatefulSetSpecBuilder {
+    spec: StatefulSetSpec,
+}
+
+impl StatefulSetSpecBuilder {
+    pub fn with_replicas(mut self, replicas: u32) -> Self {
+        self.spec.replicas = Some(replicas);
+        self
+    }
+
+    pub fn with_pod_management_policy(
+        mut self,
+        pod_management_policy: PodManagementPolicy,
+    ) -> Self {
+        self.spec.pod_management_policy = Some(pod_management_policy);
+        self
+    }
+
+    pub fn with_update_strategy(mut self, update_strategy: StatefulSetUpdateStrategy) -> Self {
+        self.spec.update_strategy = Some(update_strategy);
+        self
+    }
+
+    pub fn with_min_ready_seconds(mut self, min_ready_seconds: u32) -> Self {
+        self.spec.min_ready_seconds = Some(min_ready_seconds);
+        self
+    }
+
+    pub fn with_ordinals_start(mut self, start: u32) -> Self {
+        self.spec.ordinals = Some(StatefulSetOrdinals::new(start));
+        self
+    }
+
+    pub fn with_persistent_volume_claim_retention_policy(
+        mut self,
+        when_deleted: PersistentVolumeClaimRetentionPolicyType,
+        when_scaled: PersistentVolumeClaimRetentionPolicyType,
+    ) -> Self {
+        self.spec.persistent_volume_claim_retention_policy = Some(
+            StatefulSetPersistentVolumeClaimRetentionPolicy::new(when_deleted, when_scaled),
+        );
+        self
+    }
+
+    pub fn with_volume_claim_template(
+        mut self,
+        volume_claim_template: VolumeClaimTemplate,
+    ) -> Self {
+        self.spec.volume_claim_templates.push(volume_claim_template);
+        self
+    }
+
+    pub fn build(self) -> Result<StatefulSetSpec, ValidationErrors> {
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+
+    pub fn build_unchecked(self) -> StatefulSetSpec {
+        self.spec
     }
 }
 
-pub trait StatefulSetSpecBuilder {
-    fn with_volume_claim_template(self, volume_claim_template: VolumeClaimTemplate) -> Self;
-    fn build(self) -> StatefulSetSpec;
+impl Builder for StatefulSetSpecBuilder {}
+
+impl RollingUpdateStatefulSetStrategy {
+    pub fn new(partition: Option<u32>, max_unavailable: Option<IntOrString>) -> Self {
+        RollingUpdateStatefulSetStrategy {
+            partition,
+            max_unavailable,
+            unknown_fields: Mapping::default(),
+        }
+    }
 }
 
-impl StatefulSetSpecBuilder for Cell<StatefulSetSpec> {
-    fn with_volume_claim_template(self, volume_claim_template: VolumeClaimTemplate) -> Self {
-        let mut template = self.into_inner();
-        template.volume_claim_templates.push(volume_claim_template);
-        Cell::new(template)
+impl StatefulSetOrdinals {
+    pub fn new(start: u32) -> Self {
+        StatefulSetOrdinals {
+            start,
+            unknown_fields: Mapping::default(),
+        }
     }
+}
 
-    fn build(self) -> StatefulSetSpec {
-        self.into_inner()
+impl StatefulSetPersistentVolumeClaimRetentionPolicy {
+    pub fn new(
+        when_deleted: PersistentVolumeClaimRetentionPolicyType,
+        when_scaled: PersistentVolumeClaimRetentionPolicyType,
+    ) -> Self {
+        StatefulSetPersistentVolumeClaimRetentionPolicy {
+            when_deleted,
+            when_scaled,
+            unknown_fields: Mapping::default(),
+        }
+    }
+}
+
+impl StatefulSetSpecTemplate {
+    pub fn new<M: Into<TemplateMetadata>>(metadata: M, spec: StatefulSetSpecTemplateSpec) -> Self {
+        StatefulSetSpecTemplate {
+            metadata: metadata.into(),
+            spec,
+            unknown_fields: Mapping::default(),
+        }
     }
 }
 
 impl StatefulSetSpecTemplateSpec {
-    pub fn builder() -> Cell<StatefulSetSpecTemplateSpec> {
-        Cell::new(StatefulSetSpecTemplateSpec {
-            containers: Vec::default(),
-        })
+    pub fn builder() -> StatefulSetSpecTemplateSpecBuilder {
+        StatefulSetSpecTemplateSpecBuilder {
+            spec: StatefulSetSpecTemplateSpec {
+                containers: Vec::default(),
+                image_pull_secrets: Vec::default(),
+                termination_grace_period_seconds: None,
+                unknown_fields: Mapping::default(),
+            },
+        }
     }
 }
 
-pub trait StatefulSetSpecTemplateSpecBuilder {
-    fn with_container(self, container: Container) -> Self;
-    fn build(self) -> StatefulSetSpecTemplateSpec;
+#[derive(Debug, Clone)]
+pub struct StatefulSetSpecTemplateSpecBuilder {
+    spec: StatefulSetSpecTemplateSpec,
 }
 
-impl StatefulSetSpecTemplateSpecBuilder for Cell<StatefulSetSpecTemplateSpec> {
-    fn with_container(self, container: Container) -> Self {
-        let mut spec = self.into_inner();
-        spec.containers.push(container);
-        Cell::new(spec)
+impl StatefulSetSpecTemplateSpecBuilder {
+    pub fn with_container(mut self, container: Container) -> Self {
+        self.spec.containers.push(container);
+        self
+    }
+
+    pub fn with_image_pull_secret<S: Into<String>>(mut self, name: S) -> Self {
+        self.spec
+            .image_pull_secrets
+            .push(LocalObjectReference::new(name));
+        self
     }
 
-    fn build(self) -> StatefulSetSpecTemplateSpec {
-        self.into_inner()
+    pub fn with_termination_grace_period_seconds(mut self, seconds: u32) -> Self {
+        self.spec.termination_grace_period_seconds = Some(seconds);
+        self
+    }
+
+    pub fn build(self) -> Result<StatefulSetSpecTemplateSpec, ValidationErrors> {
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+
+    pub fn build_unchecked(self) -> StatefulSetSpecTemplateSpec {
+        self.spec
+    }
+}
+
+impl Builder for StatefulSetSpecTemplateSpecBuilder {}
+
+impl Validate for StatefulSet {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        self.spec.validate_at(&field(path, "spec"), errors);
+    }
+}
+
+impl Validate for StatefulSetSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_dns1123_label(&field(path, "serviceName"), &self.service_name);
+        self.selector.validate_at(&field(path, "selector"), errors);
+        self.template.validate_at(&field(path, "template"), errors);
+
+        let volume_claim_templates = field(path, "volumeClaimTemplates");
+        errors.validate_each(&volume_claim_templates, &self.volume_claim_templates);
+        errors.check_unique_names(
+            &volume_claim_templates,
+            self.volume_claim_templates
+                .iter()
+                .map(|template| template.metadata().name()),
+        );
+    }
+}
+
+impl Validate for StatefulSetSpecTemplate {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        self.spec.validate_at(&field(path, "spec"), errors);
+    }
+}
+
+impl Validate for StatefulSetSpecTemplateSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        let containers = field(path, "containers");
+        errors.check_not_empty(&containers, &self.containers);
+        errors.validate_each(&containers, &self.containers);
+        errors.check_unique_names(&containers, self.containers.iter().map(Container::name));
     }
 }
 
@@ -114,16 +405,16 @@ mod tests {
         let stateful_set = StatefulSet::new(
             Metadata::builder("database", "example")
                 .with_label("app", "example-db")
-                .build(),
+                .build()?,
             StatefulSetSpec::builder(
                 "example-db".into(),
                 Selector::builder()
                     .with_match_label("app", "example-db")
-                    .build(),
+                    .build()?,
                 StatefulSetSpecTemplate::new(
                     Metadata::builder("example-db", "example")
                         .with_label("app", "example-db")
-                        .build(),
+                        .build()?,
                     StatefulSetSpecTemplateSpec::builder()
                         .with_container(
                             Container::builder("pgsql", "example-db", vec!["/usr/bin/postgres"])
@@ -133,26 +424,45 @@ mod tests {
                                     "example-db-vol".into(),
                                     "/var/lib/data".into(),
                                 ))
-                                .build(),
+                                .build()?,
                         )
-                        .build(),
+                        .build()?,
                 ),
             )
             .with_volume_claim_template(VolumeClaimTemplate::new(
-                Metadata::builder("example-db-vol", "example").build(),
+                Metadata::builder("example-db-vol", "example").build()?,
                 VolumeClaimTemplateSpec::builder(VolumeClaimTemplateSpec::storage_resources(
                     "5Gi".into(),
                 ))
                 .with_access_mode(VolumeClaimAccessMode::ReadWriteOnce)
-                .build(),
+                .build()?,
+            ))
+            .with_replicas(3)
+            .with_pod_management_policy(PodManagementPolicy::Parallel)
+            .with_update_strategy(StatefulSetUpdateStrategy::rolling_update(
+                Some(2),
+                Some("50%".into()),
             ))
-            .build(),
+            .with_min_ready_seconds(10)
+            .with_ordinals_start(1)
+            .with_persistent_volume_claim_retention_policy(
+                PersistentVolumeClaimRetentionPolicyType::Retain,
+                PersistentVolumeClaimRetentionPolicyType::Delete,
+            )
+            .build()?,
         );
 
         let yaml = serde_yaml::to_string(&stateful_set)?;
 
         println!("{yaml}");
 
+        assert!(yaml.contains("replicas: 3"));
+        assert!(yaml.contains("podManagementPolicy: Parallel"));
+        assert!(yaml.contains("type: RollingUpdate"));
+        assert!(yaml.contains("partition: 2"));
+        assert!(yaml.contains("maxUnavailable: 50%"));
+        assert!(yaml.contains("whenScaled: Delete"));
+
         Ok(())
     }
 }
@@ -0,0 +1,449 @@
+use std::{collections::HashMap, fmt};
+
+use super::int_or_string::IntOrString;
+
+const DNS1123_LABEL_MAX_LENGTH: usize = 63;
+const DNS1123_SUBDOMAIN_MAX_LENGTH: usize = 253;
+const QUALIFIED_NAME_MAX_LENGTH: usize = 63;
+const LABEL_VALUE_MAX_LENGTH: usize = 63;
+const IANA_SVC_NAME_MAX_LENGTH: usize = 15;
+const TOTAL_ANNOTATION_SIZE_LIMIT: usize = 256 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    path: String,
+    message: String,
+}
+
+/// Every violation found while validating an object, with the field path of each.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors(Vec<ValidationError>);
+
+/// Structural validation against the rules the API server applies on admission.
+pub trait Validate {
+    /// Records every violation below `path`, which is empty for the object being validated.
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors);
+
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        self.validate_at("", &mut errors);
+        errors.into_result()
+    }
+}
+
+impl ValidationError {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl ValidationErrors {
+    pub fn push<P: Into<String>, M: Into<String>>(&mut self, path: P, message: M) {
+        self.0.push(ValidationError {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.0
+    }
+
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    pub fn check_dns1123_label(&mut self, path: &str, value: &str) {
+        if let Err(message) = dns1123_label(value) {
+            self.push(path, message);
+        }
+    }
+
+    pub fn check_dns1123_subdomain(&mut self, path: &str, value: &str) {
+        if let Err(message) = dns1123_subdomain(value) {
+            self.push(path, message);
+        }
+    }
+
+    pub fn check_dns1035_label(&mut self, path: &str, value: &str) {
+        if let Err(message) = dns1035_label(value) {
+            self.push(path, message);
+        }
+    }
+
+    pub fn check_labels(&mut self, path: &str, labels: &HashMap<String, String>) {
+        for (key, value) in sorted(labels) {
+            let path = field(path, key);
+            if let Err(message) = qualified_name(key) {
+                self.push(&path, format!("invalid label key: {message}"));
+            }
+            if let Err(message) = label_value(value) {
+                self.push(&path, format!("invalid label value: {message}"));
+            }
+        }
+    }
+
+    pub fn check_annotations(&mut self, path: &str, annotations: &HashMap<String, String>) {
+        let mut total_size = 0;
+        for (key, value) in sorted(annotations) {
+            if let Err(message) = qualified_name(key) {
+                self.push(
+                    field(path, key),
+                    format!("invalid annotation key: {message}"),
+                );
+            }
+            total_size += key.len() + value.len();
+        }
+
+        if total_size > TOTAL_ANNOTATION_SIZE_LIMIT {
+            self.push(
+                path,
+                format!("annotations must be at most {TOTAL_ANNOTATION_SIZE_LIMIT} bytes in total"),
+            );
+        }
+    }
+
+    pub fn check_port(&mut self, path: &str, port: u16) {
+        if port == 0 {
+            self.push(path, "must be between 1 and 65535");
+        }
+    }
+
+    pub fn check_target_port(&mut self, path: &str, port: &IntOrString) {
+        match port {
+            IntOrString::Int(port) if !(1..=65535).contains(port) => {
+                self.push(path, "must be between 1 and 65535")
+            }
+            IntOrString::String(name) => self.check_port_name(path, name),
+            IntOrString::Int(_) => {}
+        }
+    }
+
+    pub fn check_port_name(&mut self, path: &str, name: &str) {
+        if let Err(message) = iana_svc_name(name) {
+            self.push(path, message);
+        }
+    }
+
+    pub fn check_not_empty<T>(&mut self, path: &str, values: &[T]) {
+        if values.is_empty() {
+            self.push(path, "must contain at least one item");
+        }
+    }
+
+    pub fn check_unique_names<'a, I: IntoIterator<Item = &'a str>>(
+        &mut self,
+        path: &str,
+        names: I,
+    ) {
+        let mut seen = Vec::default();
+        for (i, name) in names.into_iter().enumerate() {
+            if seen.contains(&name) {
+                self.push(
+                    field(&index(path, i), "name"),
+                    format!("duplicate name {name:?}"),
+                );
+            }
+            seen.push(name);
+        }
+    }
+
+    pub fn validate_each<T: Validate>(&mut self, path: &str, values: &[T]) {
+        for (i, value) in values.iter().enumerate() {
+            value.validate_at(&index(path, i), self);
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<String> = self.0.iter().map(ValidationError::to_string).collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl IntoIterator for ValidationErrors {
+    type Item = ValidationError;
+    type IntoIter = std::vec::IntoIter<ValidationError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// The entries of a map ordered by key, so errors come out in the same order every time.
+pub(crate) fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+/// Appends a field name to a path, e.g. `spec` + `template` is `spec.template`.
+pub fn field(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.into()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+/// Appends a list index to a path, e.g. `containers` + `0` is `containers[0]`.
+pub fn index(path: &str, i: usize) -> String {
+    format!("{path}[{i}]")
+}
+
+pub fn dns1123_label(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > DNS1123_LABEL_MAX_LENGTH {
+        return Err(format!(
+            "must be between 1 and {DNS1123_LABEL_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !is_label(value, |c| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+        return Err(format!(
+            "{value:?} must consist of lower case alphanumeric characters or '-', and must start and end with an alphanumeric character"
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn dns1035_label(value: &str) -> Result<(), String> {
+    dns1123_label(value)?;
+    if !value.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err(format!("{value:?} must start with a lower case letter"));
+    }
+    Ok(())
+}
+
+pub fn dns1123_subdomain(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > DNS1123_SUBDOMAIN_MAX_LENGTH {
+        return Err(format!(
+            "must be between 1 and {DNS1123_SUBDOMAIN_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !value
+        .split('.')
+        .all(|label| is_label(label, |c| c.is_ascii_lowercase() || c.is_ascii_digit()))
+    {
+        return Err(format!(
+            "{value:?} must consist of lower case alphanumeric characters, '-' or '.', and must start and end with an alphanumeric character"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Label and annotation keys: an optional DNS subdomain prefix and a name of at most 63
+/// characters, e.g. `app.kubernetes.io/name`.
+pub fn qualified_name(value: &str) -> Result<(), String> {
+    let name = match value.split_once('/') {
+        Some((prefix, name)) => {
+            dns1123_subdomain(prefix).map_err(|message| format!("prefix {message}"))?;
+            name
+        }
+        None => value,
+    };
+
+    if name.is_empty() || name.len() > QUALIFIED_NAME_MAX_LENGTH {
+        return Err(format!(
+            "name part must be between 1 and {QUALIFIED_NAME_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !is_name(name) {
+        return Err(format!(
+            "{name:?} must consist of alphanumeric characters, '-', '_' or '.', and must start and end with an alphanumeric character"
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn label_value(value: &str) -> Result<(), String> {
+    if value.len() > LABEL_VALUE_MAX_LENGTH {
+        return Err(format!(
+            "must be at most {LABEL_VALUE_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !value.is_empty() && !is_name(value) {
+        return Err(format!(
+            "{value:?} must consist of alphanumeric characters, '-', '_' or '.', and must start and end with an alphanumeric character"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Named ports follow the IANA service name syntax.
+pub fn iana_svc_name(value: &str) -> Result<(), String> {
+    if value.is_empty() || value.len() > IANA_SVC_NAME_MAX_LENGTH {
+        return Err(format!(
+            "must be between 1 and {IANA_SVC_NAME_MAX_LENGTH} characters"
+        ));
+    }
+
+    if !is_label(value, |c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        || value.contains("--")
+        || !value.chars().any(|c| c.is_ascii_lowercase())
+    {
+        return Err(format!(
+            "{value:?} must consist of lower case alphanumeric characters or '-', contain at least one letter and not contain '--'"
+        ));
+    }
+
+    Ok(())
+}
+
+fn is_label<F: Fn(char) -> bool>(value: &str, alphanumeric: F) -> bool {
+    !value.is_empty()
+        && value.starts_with(&alphanumeric)
+        && value.ends_with(&alphanumeric)
+        && value.chars().all(|c| alphanumeric(c) || c == '-')
+}
+
+fn is_name(value: &str) -> bool {
+    let alphanumeric = |c: char| c.is_ascii_alphanumeric();
+    value.starts_with(alphanumeric)
+        && value.ends_with(alphanumeric)
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{manifest::Manifest, prelude::*};
+
+    #[test]
+    fn builders_report_every_violation() {
+        let errors = Metadata::builder("My_App", "example")
+            .with_label("app", "x".repeat(64).as_str())
+            .with_annotation("bad key!", "value")
+            .build()
+            .unwrap_err();
+
+        let paths: Vec<&str> = errors.errors().iter().map(ValidationError::path).collect();
+        assert_eq!(paths, vec!["name", "labels.app", "annotations.bad key!"]);
+
+        let errors = ServiceSpec::builder(ServiceType::ClusterIP)
+            .with_selector("app", "example")
+            .with_port(0, 8080, Some("web"))
+            .with_port(443, 8443, None::<String>)
+            .build()
+            .unwrap_err();
+
+        let paths: Vec<&str> = errors.errors().iter().map(ValidationError::path).collect();
+        assert_eq!(paths, vec!["ports[0].port", "ports[1].name"]);
+
+        let errors = Secret::builder(
+            SecretType::Opaque,
+            Metadata::builder("api", "example").build_unchecked(),
+        )
+        .with_data("z key", "".into())
+        .with_data("a key", "".into())
+        .build()
+        .unwrap_err();
+
+        let paths: Vec<&str> = errors.errors().iter().map(ValidationError::path).collect();
+        assert_eq!(paths, vec!["data.a key", "data.z key"]);
+    }
+
+    #[test]
+    fn objects_report_full_paths() {
+        let metadata = Metadata::builder("api", "example")
+            .with_label("tier", "x".repeat(64).as_str())
+            .with_label("app", "api")
+            .with_label("bad key!", "api")
+            .build_unchecked();
+        let spec = DeploymentSpec::new(
+            2,
+            Selector::builder().with_match_label("app", "api").build_unchecked(),
+            DeploymentTemplate::new(
+                DeploymentTemplateMetadata::builder("example")
+                    .with_label("app", "api")
+                    .build_unchecked(),
+                DeploymentTemplateSpec::builder()
+                    .with_container(
+                        Container::builder("example/api:v1", "Api", Vec::default())
+                            .build_unchecked(),
+                    )
+                    .build_unchecked(),
+            ),
+        );
+
+        let errors = Deployment::try_new(metadata, spec).unwrap_err();
+        let paths: Vec<&str> = errors.errors().iter().map(ValidationError::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "metadata.labels.bad key!",
+                "metadata.labels.tier",
+                "spec.template.spec.containers[0].name",
+            ]
+        );
+    }
+
+    #[test]
+    fn manifests_report_nested_paths() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: api
+spec:
+  selector:
+    matchLabels:
+      app: api
+  template:
+    metadata:
+      labels:
+        app: api
+    spec:
+      containers:
+      - name: Api
+        image: example/api:v1
+        ports:
+        - containerPort: 0
+"#;
+
+        let manifest = Manifest::parse_all(yaml)?.remove(0);
+        let errors = manifest.validate().unwrap_err();
+        let paths: Vec<&str> = errors.errors().iter().map(ValidationError::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "spec.template.spec.containers[0].name",
+                "spec.template.spec.containers[0].ports[0].containerPort",
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,44 +1,86 @@
-use std::cell::Cell;
-
-use derive_more::Constructor;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
 
-use super::metadata::Metadata;
+use super::{
+    builder::Builder,
+    metadata::Metadata,
+    validation::{field, Validate, ValidationErrors},
+};
 
-#[derive(Debug, Constructor, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct VolumeMount {
     name: String,
     mount_path: String,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Volume {
     name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
     empty_dir: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Value>")]
     config_map: Option<Value>,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Constructor, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct VolumeClaimTemplate {
     metadata: Metadata,
-    spec: VolumeClaimTemplateSpec
+    spec: VolumeClaimTemplateSpec,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct VolumeClaimTemplateSpec {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     access_modes: Vec<VolumeClaimAccessMode>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    #[schemars(with = "serde_json::Value")]
     resources: Value,
+    #[serde(flatten)]
+    #[schemars(skip)]
+    unknown_fields: Mapping,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "PascalCase")]
 pub enum VolumeClaimAccessMode {
     ReadWriteOnce,
+    ReadOnlyMany,
+    ReadWriteMany,
+    ReadWriteOncePod,
+}
+
+impl VolumeMount {
+    pub fn new(name: String, mount_path: String) -> Self {
+        VolumeMount {
+            name,
+            mount_path,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn mount_path(&self) -> &str {
+        &self.mount_path
+    }
 }
 
 impl Volume {
@@ -47,6 +89,7 @@ impl Volume {
             name,
             empty_dir: Some(Mapping::default().into()),
             config_map: None,
+            unknown_fields: Mapping::default(),
         }
     }
 
@@ -55,27 +98,76 @@ impl Volume {
             name,
             empty_dir: None,
             config_map: Some(Value::Mapping(
-                vec![(
-                    "configMap".into(),
-                    Value::Mapping(
-                        vec![("name".into(), config_map_name.into())]
-                            .into_iter()
-                            .collect(),
-                    ),
-                )]
-                .into_iter()
-                .collect(),
+                vec![("name".into(), config_map_name.into())]
+                    .into_iter()
+                    .collect(),
             )),
+            unknown_fields: Mapping::default(),
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Validate for VolumeMount {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_dns1123_label(&field(path, "name"), &self.name);
+        if self.mount_path.is_empty() {
+            errors.push(field(path, "mountPath"), "must not be empty");
+        }
+    }
+}
+
+impl Validate for Volume {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_dns1123_label(&field(path, "name"), &self.name);
+    }
+}
+
+impl Validate for VolumeClaimTemplate {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        self.metadata.validate_at(&field(path, "metadata"), errors);
+        self.spec.validate_at(&field(path, "spec"), errors);
+    }
+}
+
+impl Validate for VolumeClaimTemplateSpec {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        errors.check_not_empty(&field(path, "accessModes"), &self.access_modes);
+        if self.resources["requests"]["storage"].is_null() {
+            errors.push(
+                field(&field(&field(path, "resources"), "requests"), "storage"),
+                "must be set",
+            );
+        }
+    }
+}
+
+impl VolumeClaimTemplate {
+    pub fn new(metadata: Metadata, spec: VolumeClaimTemplateSpec) -> Self {
+        VolumeClaimTemplate {
+            metadata,
+            spec,
+            unknown_fields: Mapping::default(),
+        }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
 }
 
 impl VolumeClaimTemplateSpec {
-    pub fn builder(resources: Value) -> Cell<Self> {
-        Cell::new(VolumeClaimTemplateSpec {
-            access_modes: Vec::default(),
-            resources,
-        })
+    pub fn builder(resources: Value) -> VolumeClaimTemplateSpecBuilder {
+        VolumeClaimTemplateSpecBuilder {
+            spec: VolumeClaimTemplateSpec {
+                access_modes: Vec::default(),
+                resources,
+                unknown_fields: Mapping::default(),
+            },
+        }
     }
 
     pub fn storage_resources(amount: String) -> Value {
@@ -87,21 +179,25 @@ impl VolumeClaimTemplateSpec {
     }
 }
 
-pub trait VolumeClaimTemplateSpecBuilder {
-    fn with_access_mode(self, access_mode: VolumeClaimAccessMode) -> Self;
-    fn build(self) -> VolumeClaimTemplateSpec;
+#[derive(Debug, Clone)]
+pub struct VolumeClaimTemplateSpecBuilder {
+    spec: VolumeClaimTemplateSpec,
 }
 
-impl VolumeClaimTemplateSpecBuilder for Cell<VolumeClaimTemplateSpec> {
-    fn with_access_mode(self, access_mode: VolumeClaimAccessMode) -> Self {
-        let mut spec = self.into_inner();
-        spec.access_modes.push(access_mode);
-        Cell::new(spec)
+impl VolumeClaimTemplateSpecBuilder {
+    pub fn with_access_mode(mut self, access_mode: VolumeClaimAccessMode) -> Self {
+        self.spec.access_modes.push(access_mode);
+        self
     }
-    
-    fn build(self) -> VolumeClaimTemplateSpec {
-        self.into_inner()
+
+    pub fn build(self) -> Result<VolumeClaimTemplateSpec, ValidationErrors> {
+        self.spec.validate()?;
+        Ok(self.spec)
+    }
+
+    pub fn build_unchecked(self) -> VolumeClaimTemplateSpec {
+        self.spec
     }
+}
 
-    
-}
\ No newline at end of file
+impl Builder for VolumeClaimTemplateSpecBuilder {}
@@ -1,15 +1,37 @@
+pub mod capacity;
+pub mod check;
+pub mod compose;
+pub mod diff;
+pub mod directory;
+pub mod graph;
+pub mod helm;
+pub mod import;
+pub mod json;
 pub mod k8s;
+pub mod lint;
+pub mod manifest;
+pub mod openapi;
+pub mod order;
 pub mod recipes;
+pub mod report;
+pub mod sarif;
+pub mod yaml;
 
 pub mod prelude {
+    pub use crate::k8s::builder::Builder;
     pub use crate::k8s::container::*;
+    pub use crate::k8s::custom_resource::*;
     pub use crate::k8s::deployment::*;
     pub use crate::k8s::environment::*;
+    pub use crate::k8s::image::*;
+    pub use crate::k8s::int_or_string::*;
     pub use crate::k8s::metadata::*;
     pub use crate::k8s::namespace::*;
+    pub use crate::k8s::quantity::*;
     pub use crate::k8s::secret::*;
     pub use crate::k8s::selector::*;
     pub use crate::k8s::service::*;
     pub use crate::k8s::stateful_set::*;
+    pub use crate::k8s::validation::{Validate, ValidationError, ValidationErrors};
     pub use crate::k8s::volume::*;
 }
\ No newline at end of file
@@ -0,0 +1,443 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{
+    check::{self, str_at, ObjectRef},
+    k8s::{builder::Builder, validation::Validate},
+    manifest::{Manifest, ManifestError},
+    recipes::Environment,
+};
+
+pub mod rules;
+
+pub use rules::*;
+
+/// Annotation listing the rule ids to skip for an object, separated by commas, or `*` to
+/// skip every rule.
+pub const SUPPRESS_ANNOTATION: &str = "lint.flagship.io/ignore";
+
+/// Rule id of the findings of [`validate`] for fields the API server would reject.
+pub const VALIDATION_RULE: &str = "validation";
+
+/// Rule id of the findings of [`validate`] for references that don't resolve.
+pub const REFERENCE_RULE: &str = "dangling-reference";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// What a rule sees besides the object it checks.
+#[derive(Debug, Clone, Copy)]
+pub struct LintContext<'a> {
+    environment: &'a Environment,
+    objects: &'a [Value],
+}
+
+/// A single rule violation reported by [`Rule::check`], relative to the checked object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    path: String,
+    message: String,
+}
+
+/// A check run against every rendered object. Implement it to add team-specific rules and
+/// register them with [`LinterBuilder::with_rule`].
+pub trait Rule {
+    /// Stable kebab-case identifier, used in reports, severity overrides and suppressions.
+    fn id(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn check(&self, object: &Value, context: &LintContext) -> Vec<Violation>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    rule: String,
+    severity: Severity,
+    object: ObjectRef,
+    path: String,
+    message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LintReport {
+    findings: Vec<Finding>,
+    suppressed: usize,
+}
+
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+    severities: HashMap<String, Severity>,
+}
+
+pub struct LinterBuilder {
+    linter: Linter,
+}
+
+impl LintContext<'_> {
+    pub fn environment(&self) -> &Environment {
+        self.environment
+    }
+
+    /// Every object being linted, for rules that look across objects.
+    pub fn objects(&self) -> &[Value] {
+        self.objects
+    }
+}
+
+impl Violation {
+    pub fn new<P: Into<String>, M: Into<String>>(path: P, message: M) -> Self {
+        Violation {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Finding {
+    fn new<P: Into<String>, M: Into<String>>(
+        rule: &str,
+        object: ObjectRef,
+        path: P,
+        message: M,
+    ) -> Self {
+        Finding {
+            rule: rule.into(),
+            severity: Severity::Error,
+            object,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}")
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}: {}",
+            self.severity, self.rule, self.object, self.path, self.message
+        )
+    }
+}
+
+impl LintReport {
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// How many findings were skipped because of a suppression annotation.
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(Finding::severity).max()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.max_severity() == Some(Severity::Error)
+    }
+}
+
+impl Linter {
+    /// A linter without any rules.
+    pub fn builder() -> LinterBuilder {
+        LinterBuilder {
+            linter: Linter {
+                rules: Vec::default(),
+                severities: HashMap::default(),
+            },
+        }
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(Box::as_ref)
+    }
+
+    pub fn lint(&self, objects: &[Value], environment: &Environment) -> LintReport {
+        let context = LintContext {
+            environment,
+            objects,
+        };
+
+        let mut report = LintReport::default();
+        for object in objects {
+            let suppressed = suppressions(object);
+            for rule in &self.rules {
+                let violations = rule.check(object, &context);
+                if suppressed.iter().any(|id| *id == "*" || *id == rule.id()) {
+                    report.suppressed += violations.len();
+                    continue;
+                }
+
+                let severity = self
+                    .severities
+                    .get(rule.id())
+                    .copied()
+                    .unwrap_or_else(|| rule.severity());
+                report
+                    .findings
+                    .extend(violations.into_iter().map(|violation| Finding {
+                        rule: rule.id().into(),
+                        severity,
+                        object: ObjectRef::of(object),
+                        path: violation.path,
+                        message: violation.message,
+                    }));
+            }
+        }
+        report
+    }
+}
+
+impl Default for Linter {
+    /// A linter with every built-in rule at its default severity.
+    fn default() -> Self {
+        Linter::builder().with_default_rules().build()
+    }
+}
+
+impl LinterBuilder {
+    pub fn with_rule<R: Rule + 'static>(mut self, rule: R) -> Self {
+        self.linter.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn with_default_rules(self) -> Self {
+        self.with_rule(ContainerResources)
+            .with_rule(ImagePinned)
+            .with_rule(Probes)
+            .with_rule(NoEphemeralLoadBalancer)
+            .with_rule(NoPrivilegedContainers)
+            .with_rule(RequiredLabels::new(vec!["app"]))
+    }
+
+    /// Replaces a rule, e.g. to configure [`RequiredLabels`] differently.
+    pub fn with_rule_replaced<R: Rule + 'static>(self, rule: R) -> Self {
+        let id = rule.id().to_string();
+        self.without_rule(&id).with_rule(rule)
+    }
+
+    pub fn without_rule(mut self, id: &str) -> Self {
+        self.linter.rules.retain(|rule| rule.id() != id);
+        self
+    }
+
+    pub fn with_severity<S: Into<String>>(mut self, id: S, severity: Severity) -> Self {
+        self.linter.severities.insert(id.into(), severity);
+        self
+    }
+
+    pub fn build(self) -> Linter {
+        self.linter
+    }
+}
+
+impl Builder for LinterBuilder {}
+
+fn suppressions(object: &Value) -> Vec<&str> {
+    str_at(&object["metadata"]["annotations"][SUPPRESS_ANNOTATION])
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// The pod spec of a workload and its path, for Pods, the kinds with a pod template and
+/// CronJobs.
+pub fn pod_spec(object: &Value) -> Option<(&'static str, &Value)> {
+    let (path, pod) = match str_at(&object["kind"]) {
+        "Pod" => ("spec", &object["spec"]),
+        "Deployment" | "StatefulSet" | "DaemonSet" | "ReplicaSet" | "Job" => {
+            ("spec.template.spec", &object["spec"]["template"]["spec"])
+        }
+        "CronJob" => (
+            "spec.jobTemplate.spec.template.spec",
+            &object["spec"]["jobTemplate"]["spec"]["template"]["spec"],
+        ),
+        _ => return None,
+    };
+    pod.is_mapping().then_some((path, pod))
+}
+
+/// Structural validation of the kinds flagship models and the cross-reference checks of
+/// [`check::check`], reported as error findings under [`VALIDATION_RULE`] and
+/// [`REFERENCE_RULE`] so they can be listed alongside lint findings.
+pub fn validate(objects: &[Value]) -> Vec<Finding> {
+    let mut findings = Vec::default();
+    for object in objects {
+        match Manifest::from_value(object.clone()) {
+            Ok(manifest) => {
+                if let Err(errors) = manifest.validate() {
+                    findings.extend(errors.errors().iter().map(|error| {
+                        Finding::new(
+                            VALIDATION_RULE,
+                            ObjectRef::of(object),
+                            error.path(),
+                            error.message(),
+                        )
+                    }));
+                }
+            }
+            Err(ManifestError::MissingTypeError { path, .. }) => findings.push(Finding::new(
+                VALIDATION_RULE,
+                ObjectRef::of(object),
+                path,
+                "missing apiVersion or kind",
+            )),
+            Err(error) => findings.push(Finding::new(
+                VALIDATION_RULE,
+                ObjectRef::of(object),
+                "",
+                error.to_string(),
+            )),
+        }
+    }
+
+    findings.extend(check::check(objects).into_iter().map(|reference| {
+        Finding::new(
+            REFERENCE_RULE,
+            reference.object().clone(),
+            reference.path(),
+            reference.message(),
+        )
+    }));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::*;
+
+    struct NoDefaultNamespace;
+
+    impl Rule for NoDefaultNamespace {
+        fn id(&self) -> &str {
+            "no-default-namespace"
+        }
+
+        fn description(&self) -> &str {
+            "Objects must not be created in the default namespace"
+        }
+
+        fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+            match object["metadata"]["namespace"].as_str() {
+                Some("default") => vec![Violation::new("metadata.namespace", "is default")],
+                _ => Vec::default(),
+            }
+        }
+    }
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", false, Environment::Ephemeral("pr-1".into()))
+            .with_resource(Resource::Nginx(Nginx::new("nginx".into(), 1)))
+            .build();
+
+        let linter = Linter::builder()
+            .with_default_rules()
+            .with_rule(NoDefaultNamespace)
+            .with_severity("probes", Severity::Info)
+            .build();
+        let report = stack.lint(&linter)?;
+
+        let found: Vec<String> = report.findings().iter().map(Finding::to_string).collect();
+        assert_eq!(
+            found,
+            vec![
+                "error[no-ephemeral-load-balancer] Service example-pr-1/example-pr-1-web-svc: spec.type: LoadBalancer services are not allowed in ephemeral environments",
+                "error[container-resources] Deployment example-pr-1/example-pr-1-web: spec.template.spec.containers[0].resources: must set requests and limits",
+                "warning[image-pinned] Deployment example-pr-1/example-pr-1-web: spec.template.spec.containers[0].image: docker.io/library/nginx is not pinned to a tag other than latest or a digest",
+                "info[probes] Deployment example-pr-1/example-pr-1-web: spec.template.spec.containers[0]: must set readinessProbe and livenessProbe",
+            ]
+        );
+        assert!(report.has_errors());
+
+        Ok(())
+    }
+
+    #[test]
+    fn suppression_annotation() -> anyhow::Result<()> {
+        let object: Value = serde_yaml::from_str(
+            r#"
+apiVersion: v1
+kind: Pod
+metadata:
+  name: debug
+  namespace: default
+  annotations:
+    lint.flagship.io/ignore: container-resources, probes, image-pinned
+spec:
+  containers:
+  - name: debug
+    image: busybox
+    securityContext:
+      privileged: true
+"#,
+        )?;
+
+        let linter = Linter::builder()
+            .with_default_rules()
+            .with_rule(NoDefaultNamespace)
+            .with_rule_replaced(RequiredLabels::new(vec!["team"]))
+            .build();
+        let report = linter.lint(&[object], &Environment::Production);
+
+        let rules: Vec<&str> = report.findings().iter().map(Finding::rule).collect();
+        assert_eq!(
+            rules,
+            vec!["no-privileged", "no-default-namespace", "required-labels"]
+        );
+        assert_eq!(report.suppressed(), 3);
+        assert_eq!(
+            serde_json::to_value(&report.findings()[0])?["object"]["name"],
+            "debug"
+        );
+
+        Ok(())
+    }
+}
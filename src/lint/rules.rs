@@ -0,0 +1,232 @@
+use serde_yaml::Value;
+
+use super::{pod_spec, LintContext, Rule, Severity, Violation};
+use crate::{
+    check::{containers, str_at},
+    k8s::{image::ImageRef, validation::field},
+    recipes::Environment,
+};
+
+/// Every container sets resource requests and limits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContainerResources;
+
+/// Every image is pinned to a tag other than `latest` or to a digest.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImagePinned;
+
+/// Every long-running container has a readiness and a liveness probe.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Probes;
+
+/// No `LoadBalancer` Services in ephemeral environments, where they cost money and leak
+/// public addresses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoEphemeralLoadBalancer;
+
+/// No container runs privileged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPrivilegedContainers;
+
+/// Every object carries the given labels.
+#[derive(Debug, Clone, Default)]
+pub struct RequiredLabels {
+    labels: Vec<String>,
+}
+
+impl RequiredLabels {
+    pub fn new<S: Into<String>>(labels: Vec<S>) -> Self {
+        RequiredLabels {
+            labels: labels.into_iter().map(S::into).collect(),
+        }
+    }
+}
+
+impl Rule for ContainerResources {
+    fn id(&self) -> &str {
+        "container-resources"
+    }
+
+    fn description(&self) -> &str {
+        "Containers must set resource requests and limits"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+        each_container(object, |path, container| {
+            let resources = &container["resources"];
+            let missing: Vec<&str> = ["requests", "limits"]
+                .into_iter()
+                .filter(|key| !is_set(&resources[key]))
+                .collect();
+            match missing.len() {
+                0 => None,
+                2 => Some(Violation::new(
+                    field(path, "resources"),
+                    "must set requests and limits",
+                )),
+                _ => Some(Violation::new(
+                    field(&field(path, "resources"), missing[0]),
+                    "must be set",
+                )),
+            }
+        })
+    }
+}
+
+impl Rule for ImagePinned {
+    fn id(&self) -> &str {
+        "image-pinned"
+    }
+
+    fn description(&self) -> &str {
+        "Images must be pinned to a tag other than latest or a digest"
+    }
+
+    fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+        each_container(object, |path, container| {
+            let image = str_at(&container["image"]);
+            match ImageRef::parse(image) {
+                Ok(image) if image.is_pinned() => None,
+                Ok(image) => Some(Violation::new(
+                    field(path, "image"),
+                    format!("{image} is not pinned to a tag other than latest or a digest"),
+                )),
+                Err(error) => Some(Violation::new(field(path, "image"), error.to_string())),
+            }
+        })
+    }
+}
+
+impl Rule for Probes {
+    fn id(&self) -> &str {
+        "probes"
+    }
+
+    fn description(&self) -> &str {
+        "Long-running containers must set readiness and liveness probes"
+    }
+
+    fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+        if matches!(str_at(&object["kind"]), "Job" | "CronJob") {
+            return Vec::default();
+        }
+
+        each_container(object, |path, container| {
+            if path.contains("initContainers") {
+                return None;
+            }
+            let missing: Vec<&str> = ["readinessProbe", "livenessProbe"]
+                .into_iter()
+                .filter(|probe| !is_set(&container[probe]))
+                .collect();
+            match missing.as_slice() {
+                [] => None,
+                [probe] => Some(Violation::new(field(path, probe), "must be set")),
+                _ => Some(Violation::new(
+                    path,
+                    "must set readinessProbe and livenessProbe",
+                )),
+            }
+        })
+    }
+}
+
+impl Rule for NoEphemeralLoadBalancer {
+    fn id(&self) -> &str {
+        "no-ephemeral-load-balancer"
+    }
+
+    fn description(&self) -> &str {
+        "LoadBalancer Services are not allowed in ephemeral environments"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, object: &Value, context: &LintContext) -> Vec<Violation> {
+        let ephemeral = matches!(context.environment(), Environment::Ephemeral(_));
+        if ephemeral && object["kind"] == "Service" && object["spec"]["type"] == "LoadBalancer" {
+            vec![Violation::new(
+                "spec.type",
+                "LoadBalancer services are not allowed in ephemeral environments",
+            )]
+        } else {
+            Vec::default()
+        }
+    }
+}
+
+impl Rule for NoPrivilegedContainers {
+    fn id(&self) -> &str {
+        "no-privileged"
+    }
+
+    fn description(&self) -> &str {
+        "Containers must not run privileged"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+        each_container(object, |path, container| {
+            (container["securityContext"]["privileged"] == true).then(|| {
+                Violation::new(
+                    field(&field(path, "securityContext"), "privileged"),
+                    "must not be true",
+                )
+            })
+        })
+    }
+}
+
+impl Rule for RequiredLabels {
+    fn id(&self) -> &str {
+        "required-labels"
+    }
+
+    fn description(&self) -> &str {
+        "Objects must carry the required labels"
+    }
+
+    fn check(&self, object: &Value, _: &LintContext) -> Vec<Violation> {
+        let labels = &object["metadata"]["labels"];
+        self.labels
+            .iter()
+            .filter(|label| labels.get(label.as_str()).is_none())
+            .map(|label| {
+                Violation::new(
+                    field("metadata.labels", label),
+                    format!("required label {label:?} is missing"),
+                )
+            })
+            .collect()
+    }
+}
+
+fn each_container<F: Fn(&str, &Value) -> Option<Violation>>(
+    object: &Value,
+    check: F,
+) -> Vec<Violation> {
+    match pod_spec(object) {
+        Some((pod_path, pod)) => containers(pod_path, pod)
+            .into_iter()
+            .filter_map(|(path, container)| check(&path, container))
+            .collect(),
+        None => Vec::default(),
+    }
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        _ => true,
+    }
+}
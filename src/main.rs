@@ -0,0 +1,597 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use flagship::{
+    check::ObjectRef,
+    directory::DirectoryWriter,
+    import::{import, ImportError},
+    json::JsonFormat,
+    lint::{self, Finding, Linter, Severity},
+    manifest::{Manifest, ManifestError},
+    recipes::*,
+    sarif::SarifLog,
+};
+use serde::Serialize;
+use serde_yaml::Value;
+use thiserror::Error;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("IoError: {0}")]
+    IoError(#[from] io::Error),
+    #[error("StackError: {0}")]
+    StackError(#[from] StackError),
+    #[error("SerdeJsonError: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("ManifestError: {0}")]
+    ManifestError(#[from] ManifestError),
+    #[error("{path}: {source}")]
+    DefinitionError {
+        path: PathBuf,
+        source: Box<StackError>,
+    },
+    #[error("ImportError: {0}")]
+    ImportError(#[from] ImportError),
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+/// Renders Kubernetes manifests from declarative stack definitions.
+#[derive(Debug, Parser)]
+#[command(name = "flagship", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render a stack definition to manifests.
+    Render(RenderArgs),
+    /// Check a stack definition or a directory of manifests for fields the API server would
+    /// reject and references between objects that don't resolve.
+    Validate(CheckArgs),
+    /// Run the lint rules against a stack definition or a directory of manifests.
+    Lint(CheckArgs),
+    /// Turn existing manifests into a stack definition or Rust code, mapping what matches a
+    /// recipe to it and keeping the rest as raw objects.
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+struct RenderArgs {
+    /// Stack definition file, read as TOML when it ends in `.toml` and as YAML otherwise.
+    file: PathBuf,
+    /// Environment to render for: `production`, or the name of an ephemeral environment.
+    /// Overrides the environment of the definition.
+    #[arg(short, long)]
+    environment: Option<String>,
+    /// Output format. Directories are always written as YAML.
+    #[arg(short, long, value_enum, default_value_t = Format::Yaml, conflicts_with = "output_dir")]
+    format: Format,
+    /// Write to this file instead of stdout.
+    #[arg(short, long, conflicts_with = "output_dir")]
+    output: Option<PathBuf>,
+    /// Write one YAML file per object below this directory, with a kustomization.yaml.
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct CheckArgs {
+    /// Stack definition file, or a directory of `.yaml`, `.yml` and `.json` manifests.
+    input: PathBuf,
+    /// Environment to check for: `production`, or the name of an ephemeral environment.
+    /// Defaults to the environment of a stack definition, and to production for manifests.
+    #[arg(short, long)]
+    environment: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = ReportFormat::Text)]
+    format: ReportFormat,
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Directory the file paths in the report are relative to. Defaults to the current
+    /// directory.
+    #[arg(long, default_value = ".")]
+    base_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    /// Manifest file, or a directory of `.yaml`, `.yml` and `.json` manifests.
+    input: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = ImportFormat::Yaml)]
+    format: ImportFormat,
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ImportFormat {
+    /// A YAML stack definition.
+    Yaml,
+    /// A Rust function building the stack.
+    Rust,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    /// One line per finding, followed by a summary.
+    Text,
+    /// A JSON array of findings, each with its file and line.
+    Json,
+    /// SARIF 2.1.0, for code-scanning annotations.
+    Sarif,
+}
+
+/// The objects of a stack definition or of one manifest file, with the line each starts at.
+struct Source {
+    file: PathBuf,
+    objects: Vec<(Value, usize)>,
+}
+
+#[derive(Serialize)]
+struct Located<'a> {
+    file: String,
+    line: usize,
+    #[serde(flatten)]
+    finding: &'a Finding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Multi-document YAML.
+    Yaml,
+    /// A pretty-printed JSON List.
+    Json,
+    /// A single-line JSON List.
+    JsonCompact,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+fn load(path: &Path) -> CliResult<Stack> {
+    let definition = fs::read_to_string(path)?;
+    let stack = if path.extension().is_some_and(|e| e == "toml") {
+        Stack::from_toml(&definition)
+    } else {
+        Stack::from_yaml(&definition)
+    };
+    stack.map_err(|error| CliError::DefinitionError {
+        path: path.to_path_buf(),
+        source: Box::new(error),
+    })
+}
+
+fn environment(name: &str) -> Environment {
+    match name {
+        "production" => Environment::Production,
+        name => Environment::Ephemeral(name.into()),
+    }
+}
+
+/// Reads a directory of manifests file by file, or renders a stack definition, whose objects
+/// all point at its first line.
+fn sources(input: &Path, environment: Option<&str>) -> CliResult<(Vec<Source>, Environment)> {
+    if !input.is_dir() {
+        let mut stack = load(input)?;
+        if let Some(name) = environment {
+            stack = stack.with_environment(self::environment(name));
+        }
+        let source = Source {
+            file: input.to_path_buf(),
+            objects: stack.as_k8s()?.into_iter().map(|o| (o, 1)).collect(),
+        };
+        return Ok((vec![source], stack.environment().clone()));
+    }
+
+    let mut sources = Vec::default();
+    for file in Manifest::files(input)? {
+        let objects = manifest_objects(&file)?;
+        sources.push(Source { file, objects });
+    }
+    let environment = environment.map(self::environment);
+    Ok((sources, environment.unwrap_or(Environment::Production)))
+}
+
+/// The objects of a manifest file with the line their document starts on.
+fn manifest_objects(file: &Path) -> CliResult<Vec<(Value, usize)>> {
+    let text = fs::read_to_string(file)?;
+    Manifest::parse_values(&text).map_err(|source| {
+        CliError::ManifestError(ManifestError::FileError {
+            path: file.to_path_buf(),
+            source: Box::new(source),
+        })
+    })
+}
+
+/// `path` relative to `base` with `/` separators, as code-scanning tools expect, or as given
+/// when it is outside `base`.
+fn relative_to(path: &Path, base: &Path) -> String {
+    let absolute = |path: &Path| match env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let absolute_path = absolute(path);
+    let Ok(relative) = absolute_path.strip_prefix(absolute(base)) else {
+        return path.display().to_string();
+    };
+    relative
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether `object` is the object a finding refers to.
+fn refers_to(reference: &ObjectRef, object: &Value) -> bool {
+    let field = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    field(&object["kind"]) == reference.kind()
+        && field(&object["metadata"]["namespace"]) == reference.namespace()
+        && field(&object["metadata"]["name"]) == reference.name()
+}
+
+fn check(args: CheckArgs, lint: bool) -> CliResult<bool> {
+    let (sources, environment) = sources(&args.input, args.environment.as_deref())?;
+    let objects: Vec<Value> = sources
+        .iter()
+        .flat_map(|source| source.objects.iter().map(|(object, _)| object.clone()))
+        .collect();
+
+    let linter = Linter::default();
+    let findings = if lint {
+        linter.lint(&objects, &environment).findings().to_vec()
+    } else {
+        lint::validate(&objects)
+    };
+
+    let located: Vec<_> = findings
+        .iter()
+        .map(|finding| {
+            let (file, line) = sources
+                .iter()
+                .find_map(|source| {
+                    source
+                        .objects
+                        .iter()
+                        .find(|(object, _)| refers_to(finding.object(), object))
+                        .map(|(_, line)| (&source.file, *line))
+                })
+                .unwrap_or((&args.input, 1));
+            Located {
+                file: relative_to(file, &args.base_dir),
+                line,
+                finding,
+            }
+        })
+        .collect();
+
+    let mut report = match args.format {
+        ReportFormat::Text => {
+            let mut text = String::new();
+            for Located {
+                file,
+                line,
+                finding,
+            } in &located
+            {
+                text.push_str(&format!("{file}:{line}: {finding}\n"));
+            }
+            let count = |severity| {
+                findings
+                    .iter()
+                    .filter(|finding| finding.severity() == severity)
+                    .count()
+            };
+            text.push_str(&format!(
+                "{} errors, {} warnings, {} notes\n",
+                count(Severity::Error),
+                count(Severity::Warning),
+                count(Severity::Info)
+            ));
+            text
+        }
+        ReportFormat::Json => serde_json::to_string_pretty(&located)?,
+        ReportFormat::Sarif => {
+            let mut log = SarifLog::new("flagship", env!("CARGO_PKG_VERSION"));
+            if lint {
+                log = log.with_rules(linter.rules());
+            }
+            for Located {
+                file,
+                line,
+                finding,
+            } in &located
+            {
+                log.push(finding, file, *line);
+            }
+            serde_json::to_string_pretty(&log.to_json())?
+        }
+    };
+    if !report.ends_with('\n') {
+        report.push('\n');
+    }
+
+    match &args.output {
+        Some(path) => fs::write(path, report)?,
+        None => io::stdout().lock().write_all(report.as_bytes())?,
+    }
+    Ok(findings
+        .iter()
+        .any(|finding| finding.severity() == Severity::Error))
+}
+
+fn import_manifests(args: ImportArgs) -> CliResult<()> {
+    let files = match args.input.is_dir() {
+        true => Manifest::files(&args.input)?,
+        false => vec![args.input.clone()],
+    };
+    let mut objects = Vec::default();
+    for file in &files {
+        objects.extend(
+            manifest_objects(file)?
+                .into_iter()
+                .map(|(object, _)| object),
+        );
+    }
+
+    let import = import(&objects)?;
+    for warning in import.warnings() {
+        eprintln!("warning: {warning}");
+    }
+    let output = match args.format {
+        ImportFormat::Yaml => import.to_definition()?,
+        ImportFormat::Rust => import.to_rust()?,
+    };
+    match &args.output {
+        Some(path) => fs::write(path, output)?,
+        None => io::stdout().lock().write_all(output.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn render(args: RenderArgs) -> CliResult<()> {
+    let mut stack = load(&args.file)?;
+    if let Some(name) = &args.environment {
+        stack = stack.with_environment(environment(name));
+    }
+
+    if let Some(dir) = &args.output_dir {
+        let report = stack.write_dir(&DirectoryWriter::new(dir))?;
+        for path in report.removed() {
+            eprintln!("removed {}", dir.join(path).display());
+        }
+        return Ok(());
+    }
+
+    let mut rendered = Vec::default();
+    match args.format {
+        Format::Yaml => stack.write_yaml(&mut rendered)?,
+        Format::Json => rendered = stack.as_json(JsonFormat::Pretty)?.into_bytes(),
+        Format::JsonCompact => rendered = stack.as_json(JsonFormat::Compact)?.into_bytes(),
+        Format::JsonLines => rendered = stack.as_json(JsonFormat::Lines)?.into_bytes(),
+    }
+    if !rendered.ends_with(b"\n") {
+        rendered.push(b'\n');
+    }
+
+    match &args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => io::stdout().lock().write_all(&rendered)?,
+    }
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Render(args) => render(args).map(|()| false),
+        Command::Validate(args) => check(args, false),
+        Command::Lint(args) => check(args, true),
+        Command::Import(args) => import_manifests(args).map(|()| false),
+    };
+    match result {
+        Ok(false) => {}
+        Ok(true) => std::process::exit(1),
+        Err(error) => {
+            eprintln!("flagship: {error}");
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_yaml_and_toml_definitions() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let yaml = dir.path().join("stack.yaml");
+        fs::write(
+            &yaml,
+            r#"
+name: example
+environment: {ephemeral: pr-1}
+resources:
+  - type: postgresql
+    image: postgres:16
+  - type: microservice
+    image: ghcr.io/example/api:1.0
+    version: "1.0"
+    role: api
+    env: [{name: LOG_LEVEL, value: debug}]
+    ports: [{port: 8080, name: http}]
+"#,
+        )?;
+        let toml = dir.path().join("stack.toml");
+        fs::write(
+            &toml,
+            r#"
+name = "example"
+environment = { ephemeral = "pr-1" }
+
+[[resources]]
+type = "postgresql"
+image = "postgres:16"
+
+[[resources]]
+type = "microservice"
+image = "ghcr.io/example/api:1.0"
+version = "1.0"
+role = "api"
+env = [{ name = "LOG_LEVEL", value = "debug" }]
+ports = [{ port = 8080, name = "http" }]
+"#,
+        )?;
+
+        let from_yaml = load(&yaml)?.as_k8s()?;
+        let from_toml = load(&toml)?.as_k8s()?;
+        assert_eq!(from_yaml, from_toml);
+        assert_eq!(from_yaml[0]["metadata"]["name"], "example-pr-1");
+
+        let production = load(&yaml)?.with_environment(Environment::Production);
+        assert_eq!(production.as_k8s()?[0]["metadata"]["name"], "example");
+
+        fs::write(
+            &yaml,
+            "name: example\nresources:\n  - type: nginx\n    replica: 2\n",
+        )?;
+        let error = load(&yaml).unwrap_err().to_string();
+        assert!(error.contains("unknown field `replica`"), "{error}");
+        assert!(error.contains("line 3 column 3"), "{error}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_format_with_output_dir() {
+        let args = [
+            "flagship",
+            "render",
+            "stack.yaml",
+            "--output-dir",
+            "out",
+            "-f",
+            "json",
+        ];
+        let error = Cli::try_parse_from(args).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(Cli::try_parse_from(&args[..5]).is_ok());
+    }
+
+    #[test]
+    fn validates_manifest_directories() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let manifests = dir.path().join("manifests");
+        fs::create_dir(&manifests)?;
+        fs::write(
+            manifests.join("web.yaml"),
+            r#"# Rendered by hand.
+---
+apiVersion: v1
+kind: Namespace
+metadata: {name: example}
+---
+apiVersion: v1
+kind: Service
+metadata: {name: web, namespace: example}
+spec:
+  selector: {app: missing}
+  ports: [{port: 80, targetPort: 80}]
+"#,
+        )?;
+
+        let output = dir.path().join("findings.json");
+        let failed = check(
+            CheckArgs {
+                input: manifests,
+                environment: None,
+                format: ReportFormat::Json,
+                output: Some(output.clone()),
+                base_dir: dir.path().to_path_buf(),
+            },
+            false,
+        )?;
+        assert!(failed);
+        let findings: serde_json::Value = serde_json::from_str(&fs::read_to_string(output)?)?;
+        assert_eq!(findings[0]["rule"], lint::REFERENCE_RULE);
+        assert_eq!(findings[0]["line"], 7);
+        assert_eq!(findings[0]["file"], "manifests/web.yaml");
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_paths_outside_the_base_dir() {
+        assert_eq!(
+            relative_to(
+                Path::new("/srv/app/./manifests/web.yaml"),
+                Path::new("/srv/app")
+            ),
+            "manifests/web.yaml"
+        );
+        assert_eq!(
+            relative_to(Path::new("/tmp/web.yaml"), Path::new("/srv/app")),
+            "/tmp/web.yaml"
+        );
+    }
+
+    #[test]
+    fn accepts_standard_manifests() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let manifests = dir.path().join("manifests");
+        fs::create_dir(&manifests)?;
+        fs::write(
+            manifests.join("db.yaml"),
+            r#"apiVersion: apps/v1
+kind: StatefulSet
+metadata: {name: db}
+spec:
+  replicas: 1
+  serviceName: db
+  selector:
+    matchLabels: {app: db}
+  template:
+    metadata:
+      labels: {app: db}
+    spec:
+      containers:
+      - name: postgres
+        image: postgres:16
+        ports: [{containerPort: 5432}]
+---
+apiVersion: v1
+kind: Service
+metadata: {name: db}
+spec:
+  selector: {app: db}
+  ports: [{port: 5432}]
+"#,
+        )?;
+
+        let output = dir.path().join("findings.json");
+        let failed = check(
+            CheckArgs {
+                input: manifests,
+                environment: None,
+                format: ReportFormat::Json,
+                output: Some(output.clone()),
+                base_dir: dir.path().to_path_buf(),
+            },
+            false,
+        )?;
+        let findings: serde_json::Value = serde_json::from_str(&fs::read_to_string(output)?)?;
+        assert_eq!(findings, serde_json::json!([]));
+        assert!(!failed);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_yaml::Value;
+use thiserror::Error;
+
+use crate::prelude::*;
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("IoError: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("document {document}: {source}")]
+    SyntaxError {
+        document: usize,
+        source: serde_yaml::Error,
+    },
+    #[error("document {document}: {path}: missing apiVersion or kind")]
+    MissingTypeError { document: usize, path: String },
+    #[error("document {document}: {path}: {message}")]
+    FieldError {
+        document: usize,
+        path: String,
+        message: String,
+    },
+    #[error("{path}: {source}")]
+    FileError {
+        path: PathBuf,
+        source: Box<ManifestError>,
+    },
+}
+
+pub type ManifestResult<T> = Result<T, ManifestError>;
+
+/// A single Kubernetes object read from a manifest, typed where flagship models the kind.
+#[derive(Debug)]
+pub enum Manifest {
+    Namespace(Namespace),
+    Deployment(Box<Deployment>),
+    StatefulSet(Box<StatefulSet>),
+    Service(Service),
+    Secret(Secret),
+    Unsupported(Value),
+}
+
+impl Manifest {
+    /// Parses a multi-document YAML stream, such as the output of `kubectl get -o yaml` or a
+    /// file of `---` separated manifests. `kind: List` documents are flattened into their items
+    /// and empty documents are skipped.
+    pub fn parse_all(yaml: &str) -> ManifestResult<Vec<Manifest>> {
+        let mut manifests = Vec::default();
+        for (document, de) in serde_yaml::Deserializer::from_str(yaml).enumerate() {
+            let value = Value::deserialize(de)
+                .map_err(|source| ManifestError::SyntaxError { document, source })?;
+            flatten(value, document, String::new(), &mut |value, path| {
+                manifests.push(Manifest::typed(value, document, path)?);
+                Ok(())
+            })?;
+        }
+        Ok(manifests)
+    }
+
+    /// Like [`Manifest::parse_all`], but keeps the objects untyped, each with the line its
+    /// document starts on, so problems found later can be pointed at in the file.
+    pub fn parse_values(yaml: &str) -> ManifestResult<Vec<(Value, usize)>> {
+        let lines = document_lines(yaml);
+        let mut values = Vec::default();
+        for (document, de) in serde_yaml::Deserializer::from_str(yaml).enumerate() {
+            let line = lines.get(document).copied().unwrap_or(1);
+            let value = Value::deserialize(de)
+                .map_err(|source| ManifestError::SyntaxError { document, source })?;
+            flatten(value, document, String::new(), &mut |value, _| {
+                values.push((value, line));
+                Ok(())
+            })?;
+        }
+        Ok(values)
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> ManifestResult<Vec<Manifest>> {
+        let mut yaml = String::new();
+        reader.read_to_string(&mut yaml)?;
+        Manifest::parse_all(&yaml)
+    }
+
+    /// Reads every `.yaml`, `.yml` and `.json` file below `dir`, in path order.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> ManifestResult<Vec<Manifest>> {
+        let mut manifests = Vec::default();
+        for path in Manifest::files(dir)? {
+            let yaml = fs::read_to_string(&path)?;
+            let mut parsed =
+                Manifest::parse_all(&yaml).map_err(|source| ManifestError::FileError {
+                    path: path.clone(),
+                    source: Box::new(source),
+                })?;
+            manifests.append(&mut parsed);
+        }
+        Ok(manifests)
+    }
+
+    /// Like [`Manifest::load_dir`], but keeps the objects untyped, exactly as written.
+    pub fn load_dir_values<P: AsRef<Path>>(dir: P) -> ManifestResult<Vec<Value>> {
+        let mut values = Vec::default();
+        for path in Manifest::files(dir)? {
+            let yaml = fs::read_to_string(&path)?;
+            let parsed =
+                Manifest::parse_values(&yaml).map_err(|source| ManifestError::FileError {
+                    path: path.clone(),
+                    source: Box::new(source),
+                })?;
+            values.extend(parsed.into_iter().map(|(value, _)| value));
+        }
+        Ok(values)
+    }
+
+    /// The `.yaml`, `.yml` and `.json` files below `dir` that [`Manifest::load_dir`] reads, in
+    /// path order.
+    pub fn files<P: AsRef<Path>>(dir: P) -> std::io::Result<Vec<PathBuf>> {
+        let mut files = Vec::default();
+        collect_files(dir.as_ref(), &mut files)?;
+        files.sort();
+        Ok(files)
+    }
+
+    /// Converts a single object, dispatching on its `apiVersion` and `kind`.
+    pub fn from_value(value: Value) -> ManifestResult<Manifest> {
+        Manifest::typed(value, 0, String::new())
+    }
+
+    pub fn api_version(&self) -> Option<&str> {
+        match self {
+            Manifest::Namespace(_) | Manifest::Service(_) | Manifest::Secret(_) => Some("v1"),
+            Manifest::Deployment(_) | Manifest::StatefulSet(_) => Some("apps/v1"),
+            Manifest::Unsupported(value) => value["apiVersion"].as_str(),
+        }
+    }
+
+    pub fn kind(&self) -> Option<&str> {
+        match self {
+            Manifest::Namespace(_) => Some("Namespace"),
+            Manifest::Deployment(_) => Some("Deployment"),
+            Manifest::StatefulSet(_) => Some("StatefulSet"),
+            Manifest::Service(_) => Some("Service"),
+            Manifest::Secret(_) => Some("Secret"),
+            Manifest::Unsupported(value) => value["kind"].as_str(),
+        }
+    }
+
+    pub fn to_value(&self) -> Result<Value, serde_yaml::Error> {
+        serde_yaml::to_value(self)
+    }
+
+    /// Objects of a modeled kind that don't fit flagship's types, such as ones using fields or
+    /// values flagship doesn't know, are kept as [`Manifest::Unsupported`] rather than
+    /// rejected, since the API server may well accept them.
+    fn typed(value: Value, document: usize, path: String) -> ManifestResult<Manifest> {
+        let api_version = value["apiVersion"].as_str();
+        let kind = value["kind"].as_str();
+
+        let manifest = match (api_version, kind) {
+            (Some("v1"), Some("Namespace")) => deserialize(&value).map(Manifest::Namespace),
+            (Some("apps/v1"), Some("Deployment")) => deserialize(&value).map(Manifest::Deployment),
+            (Some("apps/v1"), Some("StatefulSet")) => {
+                deserialize(&value).map(Manifest::StatefulSet)
+            }
+            (Some("v1"), Some("Service")) => deserialize(&value).map(Manifest::Service),
+            (Some("v1"), Some("Secret")) => deserialize(&value).map(Manifest::Secret),
+            (Some(_), Some(_)) => None,
+            _ => return Err(ManifestError::MissingTypeError { document, path }),
+        };
+
+        Ok(manifest.unwrap_or(Manifest::Unsupported(value)))
+    }
+}
+
+impl Serialize for Manifest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Manifest::Namespace(namespace) => namespace.serialize(serializer),
+            Manifest::Deployment(deployment) => deployment.serialize(serializer),
+            Manifest::StatefulSet(stateful_set) => stateful_set.serialize(serializer),
+            Manifest::Service(service) => service.serialize(serializer),
+            Manifest::Secret(secret) => secret.serialize(serializer),
+            Manifest::Unsupported(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl Validate for Manifest {
+    fn validate_at(&self, path: &str, errors: &mut ValidationErrors) {
+        match self {
+            Manifest::Namespace(namespace) => namespace.validate_at(path, errors),
+            Manifest::Deployment(deployment) => deployment.validate_at(path, errors),
+            Manifest::StatefulSet(stateful_set) => stateful_set.validate_at(path, errors),
+            Manifest::Service(service) => service.validate_at(path, errors),
+            Manifest::Secret(secret) => secret.validate_at(path, errors),
+            Manifest::Unsupported(_) => {}
+        }
+    }
+}
+
+/// Calls `f` for every object of a document, with its path, descending into `kind: List`
+/// documents and skipping empty ones.
+fn flatten<F: FnMut(Value, String) -> ManifestResult<()>>(
+    value: Value,
+    document: usize,
+    path: String,
+    f: &mut F,
+) -> ManifestResult<()> {
+    if value.is_null() {
+        return Ok(());
+    }
+
+    if value["apiVersion"] == "v1" && value["kind"] == "List" {
+        let items = match value {
+            Value::Mapping(mut mapping) => mapping.remove("items"),
+            _ => None,
+        };
+        let items = match items {
+            Some(Value::Sequence(items)) => items,
+            Some(Value::Null) | None => Vec::default(),
+            Some(_) => {
+                return Err(ManifestError::FieldError {
+                    document,
+                    path: join_path(&path, "items"),
+                    message: "expected a sequence of objects".into(),
+                })
+            }
+        };
+
+        for (i, item) in items.into_iter().enumerate() {
+            let path = join_path(&path, &format!("items[{i}]"));
+            flatten(item, document, path, f)?;
+        }
+        return Ok(());
+    }
+
+    f(value, path)
+}
+
+/// The first line of every `---` separated document, skipping blank and comment lines.
+fn document_lines(text: &str) -> Vec<usize> {
+    let mut documents: Vec<(usize, Option<usize>)> = vec![(1, None)];
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if line.starts_with("---") {
+            documents.push((i + 2, None));
+        } else if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            documents.last_mut().unwrap().1.get_or_insert(i + 1);
+        }
+    }
+    if documents.len() > 1 && documents[0].1.is_none() {
+        documents.remove(0);
+    }
+    documents
+        .into_iter()
+        .map(|(start, content)| content.unwrap_or(start))
+        .collect()
+}
+
+fn deserialize<'de, T: Deserialize<'de>>(value: &'de Value) -> Option<T> {
+    T::deserialize(value).ok()
+}
+
+fn join_path(prefix: &str, path: &str) -> String {
+    match (prefix.is_empty(), path) {
+        (true, _) => path.into(),
+        (false, ".") => prefix.into(),
+        (false, _) => format!("{prefix}.{path}"),
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml" | "json")
+        ) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFESTS: &str = r#"
+apiVersion: v1
+kind: List
+items:
+- apiVersion: v1
+  kind: Service
+  metadata:
+    name: api-svc
+    namespace: example
+  spec:
+    selector:
+      app: api
+    ports:
+    - port: 80
+      targetPort: http
+- apiVersion: apps/v1
+  kind: Deployment
+  metadata:
+    name: api
+    namespace: example
+  spec:
+    replicas: 2
+    selector:
+      matchLabels:
+        app: api
+    template:
+      metadata:
+        labels:
+          app: api
+      spec:
+        containers:
+        - name: api
+          image: example/api:v1
+          env:
+          - name: PASSWORD
+            valueFrom:
+              secretKeyRef:
+                name: api
+                key: password
+---
+apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: api
+"#;
+
+    #[test]
+    fn parse_all() -> anyhow::Result<()> {
+        let manifests = Manifest::parse_all(MANIFESTS)?;
+
+        assert_eq!(manifests.len(), 3);
+        assert!(matches!(manifests[0], Manifest::Service(_)));
+        assert!(matches!(manifests[1], Manifest::Deployment(_)));
+        assert!(matches!(manifests[2], Manifest::Unsupported(_)));
+        assert_eq!(manifests[2].kind(), Some("Ingress"));
+
+        let deployment = manifests[1].to_value()?;
+        let env = &deployment["spec"]["template"]["spec"]["containers"][0]["env"][0];
+        assert_eq!(
+            env["valueFrom"]["secretKeyRef"]["key"].as_str(),
+            Some("password")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn preserves_unknown_fields() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: api
+  namespace: example
+  annotations:
+    deployment.kubernetes.io/revision: "4"
+  uid: 0d5c0b8e-8d0f-4c1e-9a4e-2f0b6f0d9a11
+spec:
+  revisionHistoryLimit: 5
+  strategy:
+    type: RollingUpdate
+  selector:
+    matchLabels:
+      app: api
+  template:
+    metadata:
+      labels:
+        app: api
+    spec:
+      nodeSelector:
+        pool: general
+      containers:
+      - name: api
+        image: example/api:v1
+        readinessProbe:
+          httpGet:
+            path: /ready
+            port: 8080
+        ports:
+        - containerPort: 8080
+          name: http
+        volumeMounts:
+        - name: config
+          mountPath: /etc/api
+          readOnly: true
+      volumes:
+      - name: config
+        secret:
+          secretName: api-config
+status:
+  readyReplicas: 2
+"#;
+
+        let manifests = Manifest::parse_all(yaml)?;
+        assert!(matches!(manifests[0], Manifest::Deployment(_)));
+        assert_eq!(
+            manifests[0].to_value()?,
+            serde_yaml::from_str::<Value>(yaml)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_kubectl_output() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  annotations:
+    deployment.kubernetes.io/revision: "1"
+  creationTimestamp: "2024-05-02T09:14:11Z"
+  generation: 1
+  labels:
+    app: api
+  name: api
+  namespace: example
+  resourceVersion: "48213"
+  uid: 6b1f0a52-93c4-4f3e-8f0c-1c2d3e4f5a6b
+spec:
+  progressDeadlineSeconds: 600
+  replicas: 1
+  revisionHistoryLimit: 10
+  selector:
+    matchLabels:
+      app: api
+  strategy:
+    rollingUpdate:
+      maxSurge: 25%
+      maxUnavailable: 25%
+    type: RollingUpdate
+  template:
+    metadata:
+      creationTimestamp: null
+      labels:
+        app: api
+    spec:
+      containers:
+      - env:
+        - name: POD_NAME
+          valueFrom:
+            fieldRef:
+              apiVersion: v1
+              fieldPath: metadata.name
+        - name: PASSWORD
+          valueFrom:
+            secretKeyRef:
+              key: password
+              name: api
+        - name: FEATURES
+          valueFrom:
+            configMapKeyRef:
+              key: features
+              name: api
+              optional: true
+        - name: MEMORY_LIMIT
+          valueFrom:
+            resourceFieldRef:
+              containerName: api
+              divisor: "0"
+              resource: limits.memory
+        image: example/api:v1
+        imagePullPolicy: IfNotPresent
+        name: api
+        resources: {}
+        terminationMessagePath: /dev/termination-log
+        terminationMessagePolicy: File
+      dnsPolicy: ClusterFirst
+      restartPolicy: Always
+      schedulerName: default-scheduler
+      securityContext: {}
+      terminationGracePeriodSeconds: 30
+status:
+  availableReplicas: 1
+  observedGeneration: 1
+  readyReplicas: 1
+  replicas: 1
+  updatedReplicas: 1
+"#;
+
+        let manifests = Manifest::parse_all(yaml)?;
+        assert!(matches!(manifests[0], Manifest::Deployment(_)));
+        assert_eq!(
+            manifests[0].to_value()?,
+            serde_yaml::from_str::<Value>(yaml)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_values_with_lines() -> anyhow::Result<()> {
+        let yaml = "# Rendered by hand.\n---\napiVersion: v1\nkind: Namespace\n".to_string()
+            + "metadata: {name: example}\n---\n\napiVersion: v1\nkind: List\nitems:\n"
+            + "- {apiVersion: v1, kind: ConfigMap, metadata: {name: a}}\n"
+            + "- {apiVersion: v1, kind: Secret, metadata: {name: b}}\n";
+
+        let values = Manifest::parse_values(&yaml)?;
+        let located: Vec<(&str, usize)> = values
+            .iter()
+            .map(|(value, line)| (value["kind"].as_str().unwrap_or_default(), *line))
+            .collect();
+        assert_eq!(
+            located,
+            vec![("Namespace", 3), ("ConfigMap", 8), ("Secret", 8)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_document_and_path() {
+        let yaml = "apiVersion: v1\nkind: Namespace\nmetadata:\n  name: ok\n---\n".to_string()
+            + "apiVersion: v1\nkind: List\nitems:\n- apiVersion: v1\n  kind: List\n"
+            + "  items: {name: api}\n";
+
+        match Manifest::parse_all(&yaml) {
+            Err(ManifestError::FieldError { document, path, .. }) => {
+                assert_eq!(document, 1);
+                assert_eq!(path, "items[0].items");
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn loads_standard_manifests() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: StatefulSet
+metadata:
+  generateName: db-
+spec:
+  replicas: 100000
+  serviceName: db
+  selector:
+    matchLabels: {app: db}
+  template:
+    metadata:
+      labels: {app: db}
+    spec:
+      containers:
+      - name: postgres
+        image: postgres:16
+        env:
+        - name: PASSWORD
+          valueFrom:
+            fileKeyRef: {path: db.env, key: PASSWORD, volumeName: config}
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: db
+spec:
+  selector: {app: db}
+  ports:
+  - port: 5432
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: token
+type: bootstrap.kubernetes.io/token
+"#;
+
+        let manifests = Manifest::parse_all(yaml)?;
+        assert!(matches!(manifests[0], Manifest::StatefulSet(_)));
+        assert!(matches!(manifests[1], Manifest::Service(_)));
+        assert!(matches!(manifests[2], Manifest::Secret(_)));
+        for (manifest, (value, _)) in manifests.iter().zip(Manifest::parse_values(yaml)?) {
+            manifest.validate()?;
+            assert_eq!(manifest.to_value()?, value);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_objects_that_do_not_fit_as_unsupported() -> anyhow::Result<()> {
+        let yaml = "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: api\n".to_string()
+            + "spec:\n  replicas: many\n";
+
+        let manifests = Manifest::parse_all(&yaml)?;
+        assert!(matches!(&manifests[..], [Manifest::Unsupported(value)]
+            if value["spec"]["replicas"] == "many"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use super::KubernetesVersion;
+
+/// A served apiVersion of a kind that has been deprecated and possibly removed, following
+/// the upstream deprecated API migration guide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiDeprecation {
+    api_version: &'static str,
+    kind: &'static str,
+    deprecated_in: KubernetesVersion,
+    removed_in: Option<KubernetesVersion>,
+    replacement: &'static str,
+}
+
+impl ApiDeprecation {
+    const fn new(
+        api_version: &'static str,
+        kind: &'static str,
+        deprecated_in: u32,
+        removed_in: Option<u32>,
+        replacement: &'static str,
+    ) -> Self {
+        ApiDeprecation {
+            api_version,
+            kind,
+            deprecated_in: KubernetesVersion::new(1, deprecated_in),
+            removed_in: match removed_in {
+                Some(minor) => Some(KubernetesVersion::new(1, minor)),
+                None => None,
+            },
+            replacement,
+        }
+    }
+
+    pub fn api_version(&self) -> &str {
+        self.api_version
+    }
+
+    pub fn kind(&self) -> &str {
+        self.kind
+    }
+
+    pub fn deprecated_in(&self) -> KubernetesVersion {
+        self.deprecated_in
+    }
+
+    pub fn removed_in(&self) -> Option<KubernetesVersion> {
+        self.removed_in
+    }
+
+    /// The apiVersion to migrate to.
+    pub fn replacement(&self) -> &str {
+        self.replacement
+    }
+}
+
+/// Looks up the deprecation of `kind` in `api_version`, if any.
+pub fn deprecation(api_version: &str, kind: &str) -> Option<&'static ApiDeprecation> {
+    API_DEPRECATIONS
+        .iter()
+        .find(|d| d.api_version == api_version && d.kind == kind)
+}
+
+#[rustfmt::skip]
+pub const API_DEPRECATIONS: &[ApiDeprecation] = &[
+    ApiDeprecation::new("extensions/v1beta1", "Deployment", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("extensions/v1beta1", "DaemonSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("extensions/v1beta1", "ReplicaSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("extensions/v1beta1", "NetworkPolicy", 9, Some(16), "networking.k8s.io/v1"),
+    ApiDeprecation::new("extensions/v1beta1", "PodSecurityPolicy", 11, Some(16), "policy/v1beta1"),
+    ApiDeprecation::new("extensions/v1beta1", "Ingress", 14, Some(22), "networking.k8s.io/v1"),
+    ApiDeprecation::new("apps/v1beta1", "Deployment", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("apps/v1beta1", "StatefulSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("apps/v1beta2", "Deployment", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("apps/v1beta2", "StatefulSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("apps/v1beta2", "DaemonSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("apps/v1beta2", "ReplicaSet", 9, Some(16), "apps/v1"),
+    ApiDeprecation::new("networking.k8s.io/v1beta1", "Ingress", 19, Some(22), "networking.k8s.io/v1"),
+    ApiDeprecation::new("networking.k8s.io/v1beta1", "IngressClass", 19, Some(22), "networking.k8s.io/v1"),
+    ApiDeprecation::new("rbac.authorization.k8s.io/v1beta1", "ClusterRole", 17, Some(22), "rbac.authorization.k8s.io/v1"),
+    ApiDeprecation::new("rbac.authorization.k8s.io/v1beta1", "ClusterRoleBinding", 17, Some(22), "rbac.authorization.k8s.io/v1"),
+    ApiDeprecation::new("rbac.authorization.k8s.io/v1beta1", "Role", 17, Some(22), "rbac.authorization.k8s.io/v1"),
+    ApiDeprecation::new("rbac.authorization.k8s.io/v1beta1", "RoleBinding", 17, Some(22), "rbac.authorization.k8s.io/v1"),
+    ApiDeprecation::new("apiextensions.k8s.io/v1beta1", "CustomResourceDefinition", 16, Some(22), "apiextensions.k8s.io/v1"),
+    ApiDeprecation::new("admissionregistration.k8s.io/v1beta1", "MutatingWebhookConfiguration", 16, Some(22), "admissionregistration.k8s.io/v1"),
+    ApiDeprecation::new("admissionregistration.k8s.io/v1beta1", "ValidatingWebhookConfiguration", 16, Some(22), "admissionregistration.k8s.io/v1"),
+    ApiDeprecation::new("scheduling.k8s.io/v1beta1", "PriorityClass", 14, Some(22), "scheduling.k8s.io/v1"),
+    ApiDeprecation::new("coordination.k8s.io/v1beta1", "Lease", 19, Some(22), "coordination.k8s.io/v1"),
+    ApiDeprecation::new("batch/v1beta1", "CronJob", 21, Some(25), "batch/v1"),
+    ApiDeprecation::new("policy/v1beta1", "PodDisruptionBudget", 21, Some(25), "policy/v1"),
+    ApiDeprecation::new("policy/v1beta1", "PodSecurityPolicy", 21, Some(25), "Pod Security Admission"),
+    ApiDeprecation::new("discovery.k8s.io/v1beta1", "EndpointSlice", 21, Some(25), "discovery.k8s.io/v1"),
+    ApiDeprecation::new("events.k8s.io/v1beta1", "Event", 19, Some(25), "events.k8s.io/v1"),
+    ApiDeprecation::new("node.k8s.io/v1beta1", "RuntimeClass", 20, Some(25), "node.k8s.io/v1"),
+    ApiDeprecation::new("autoscaling/v2beta1", "HorizontalPodAutoscaler", 22, Some(25), "autoscaling/v2"),
+    ApiDeprecation::new("autoscaling/v2beta2", "HorizontalPodAutoscaler", 23, Some(26), "autoscaling/v2"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta1", "FlowSchema", 23, Some(26), "flowcontrol.apiserver.k8s.io/v1"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta1", "PriorityLevelConfiguration", 23, Some(26), "flowcontrol.apiserver.k8s.io/v1"),
+    ApiDeprecation::new("storage.k8s.io/v1beta1", "CSIStorageCapacity", 24, Some(27), "storage.k8s.io/v1"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta2", "FlowSchema", 26, Some(29), "flowcontrol.apiserver.k8s.io/v1"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta2", "PriorityLevelConfiguration", 26, Some(29), "flowcontrol.apiserver.k8s.io/v1"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta3", "FlowSchema", 29, Some(32), "flowcontrol.apiserver.k8s.io/v1"),
+    ApiDeprecation::new("flowcontrol.apiserver.k8s.io/v1beta3", "PriorityLevelConfiguration", 29, Some(32), "flowcontrol.apiserver.k8s.io/v1"),
+];
@@ -0,0 +1,512 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+use crate::{
+    check::{sequence, str_at, ObjectRef},
+    k8s::validation::{field, index},
+};
+
+pub mod deprecations;
+
+pub use deprecations::{deprecation, ApiDeprecation, API_DEPRECATIONS};
+
+const SUBSET: &str = include_str!("schemas/subset.yaml");
+
+/// Changes per minor version on top of [`SUBSET`], in version order.
+const OVERLAYS: &[(KubernetesVersion, &str)] = &[
+    (
+        KubernetesVersion::new(1, 28),
+        include_str!("schemas/v1.28.yaml"),
+    ),
+    (
+        KubernetesVersion::new(1, 29),
+        include_str!("schemas/v1.29.yaml"),
+    ),
+    (
+        KubernetesVersion::new(1, 30),
+        include_str!("schemas/v1.30.yaml"),
+    ),
+    (
+        KubernetesVersion::new(1, 31),
+        include_str!("schemas/v1.31.yaml"),
+    ),
+];
+
+const OLDEST: KubernetesVersion = KubernetesVersion::new(1, 27);
+const LATEST: KubernetesVersion = KubernetesVersion::new(1, 31);
+const REF_PREFIX: &str = "#/components/schemas/";
+
+#[derive(Debug, Error)]
+pub enum OpenApiError {
+    #[error("InvalidVersionError: {0:?} is not a Kubernetes version such as 1.29")]
+    InvalidVersionError(String),
+    #[error("UnsupportedVersionError: no bundled schemas for Kubernetes {0}, supported versions are {OLDEST} to {LATEST}")]
+    UnsupportedVersionError(KubernetesVersion),
+    #[error("SerdeYamlError: {0}")]
+    SerdeYamlError(#[from] serde_yaml::Error),
+}
+
+pub type OpenApiResult<T> = Result<T, OpenApiError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KubernetesVersion {
+    major: u32,
+    minor: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    UnknownField,
+    TypeMismatch,
+    MissingField,
+    RemovedApiVersion,
+    DeprecatedApiVersion,
+}
+
+/// A difference between an object and the schema of the target Kubernetes version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    kind: ViolationKind,
+    object: ObjectRef,
+    path: String,
+    message: String,
+}
+
+/// A best-effort check against a hand-written subset of the OpenAPI schemas of one Kubernetes
+/// minor version. Only the kinds flagship renders are covered, and only the fields flagship
+/// knows about, so it catches common mistakes but is no substitute for a server-side dry run.
+/// Objects of other kinds are checked for removed and deprecated apiVersions only.
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    version: KubernetesVersion,
+    schemas: Mapping,
+    kinds: HashMap<(String, String), String>,
+}
+
+impl KubernetesVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        KubernetesVersion { major, minor }
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Every version with bundled schemas, oldest first.
+    pub fn supported() -> Vec<KubernetesVersion> {
+        (OLDEST.minor..=LATEST.minor)
+            .map(|minor| KubernetesVersion::new(1, minor))
+            .collect()
+    }
+}
+
+impl fmt::Display for KubernetesVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for KubernetesVersion {
+    type Err = OpenApiError;
+
+    /// Parses `1.29`, `v1.29` or a full release such as `v1.29.3`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OpenApiError::InvalidVersionError(s.into());
+        let mut parts = s.strip_prefix('v').unwrap_or(s).split('.');
+        let major = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        let minor = parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(invalid)?;
+        match parts.next() {
+            Some(patch) if patch.parse::<u32>().is_err() => Err(invalid()),
+            _ if parts.next().is_some() => Err(invalid()),
+            _ => Ok(KubernetesVersion::new(major, minor)),
+        }
+    }
+}
+
+impl SchemaViolation {
+    pub fn kind(&self) -> ViolationKind {
+        self.kind
+    }
+
+    pub fn object(&self) -> &ObjectRef {
+        &self.object
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Deprecated apiVersions still work, and unknown fields may just be missing from the
+    /// bundled subset. Every other violation is rejected by the API server.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self.kind,
+            ViolationKind::DeprecatedApiVersion | ViolationKind::UnknownField
+        )
+    }
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.object, self.path, self.message)
+    }
+}
+
+impl OpenApi {
+    pub fn for_version(version: KubernetesVersion) -> OpenApiResult<OpenApi> {
+        if version < OLDEST || version > LATEST {
+            return Err(OpenApiError::UnsupportedVersionError(version));
+        }
+
+        let mut schemas = components(SUBSET)?;
+        for (_, overlay) in OVERLAYS.iter().filter(|(since, _)| *since <= version) {
+            for (name, schema) in components(overlay)? {
+                match schemas.get_mut(&name) {
+                    Some(existing) => merge(existing, schema),
+                    None => {
+                        schemas.insert(name, schema);
+                    }
+                }
+            }
+        }
+
+        let mut kinds = HashMap::default();
+        for (name, schema) in &schemas {
+            for gvk in sequence(&schema["x-kubernetes-group-version-kind"]) {
+                let api_version = match str_at(&gvk["group"]) {
+                    "" => str_at(&gvk["version"]).to_string(),
+                    group => format!("{group}/{}", str_at(&gvk["version"])),
+                };
+                kinds.insert(
+                    (api_version, str_at(&gvk["kind"]).to_string()),
+                    str_at(name).to_string(),
+                );
+            }
+        }
+
+        Ok(OpenApi {
+            version,
+            schemas,
+            kinds,
+        })
+    }
+
+    /// The schemas of the newest bundled version.
+    pub fn latest() -> OpenApi {
+        OpenApi::for_version(LATEST).expect("bundled schemas are valid")
+    }
+
+    pub fn version(&self) -> KubernetesVersion {
+        self.version
+    }
+
+    pub fn validate(&self, objects: &[Value]) -> Vec<SchemaViolation> {
+        objects
+            .iter()
+            .flat_map(|object| self.validate_object(object))
+            .collect()
+    }
+
+    pub fn validate_object(&self, object: &Value) -> Vec<SchemaViolation> {
+        let mut validator = Validator {
+            openapi: self,
+            object: ObjectRef::of(object),
+            found: Vec::default(),
+        };
+
+        let api_version = str_at(&object["apiVersion"]);
+        let kind = str_at(&object["kind"]);
+        if let Some(deprecation) = deprecation(api_version, kind) {
+            match deprecation.removed_in() {
+                Some(removed_in) if removed_in <= self.version => validator.push(
+                    ViolationKind::RemovedApiVersion,
+                    "apiVersion",
+                    format!(
+                        "{api_version} {kind} was removed in Kubernetes {removed_in}, use {}",
+                        deprecation.replacement()
+                    ),
+                ),
+                _ if deprecation.deprecated_in() <= self.version => validator.push(
+                    ViolationKind::DeprecatedApiVersion,
+                    "apiVersion",
+                    format!(
+                        "{api_version} {kind} is deprecated since Kubernetes {}, use {}",
+                        deprecation.deprecated_in(),
+                        deprecation.replacement()
+                    ),
+                ),
+                _ => {}
+            }
+        }
+
+        let key = (api_version.to_string(), kind.to_string());
+        if let Some(name) = self.kinds.get(&key) {
+            validator.walk(&self.schemas[name.as_str()], object, "");
+        }
+
+        validator.found
+    }
+
+    fn resolve<'a>(&'a self, schema: &'a Value) -> &'a Value {
+        match schema["$ref"].as_str() {
+            Some(reference) => {
+                let name = reference.strip_prefix(REF_PREFIX).unwrap_or(reference);
+                self.resolve(&self.schemas[name])
+            }
+            None => schema,
+        }
+    }
+}
+
+struct Validator<'a> {
+    openapi: &'a OpenApi,
+    object: ObjectRef,
+    found: Vec<SchemaViolation>,
+}
+
+impl Validator<'_> {
+    fn push<P: Into<String>, M: Into<String>>(&mut self, kind: ViolationKind, path: P, message: M) {
+        self.found.push(SchemaViolation {
+            kind,
+            object: self.object.clone(),
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn walk(&mut self, schema: &Value, value: &Value, path: &str) {
+        // Explicit nulls are treated as unset by the API server.
+        if value.is_null() {
+            return;
+        }
+
+        let schema = self.openapi.resolve(schema);
+        let expected = str_at(&schema["type"]);
+        let format = str_at(&schema["format"]);
+        let matches = match (expected, value) {
+            ("object", Value::Mapping(mapping)) => {
+                self.walk_object(schema, mapping, path);
+                true
+            }
+            ("array", Value::Sequence(items)) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.walk(&schema["items"], item, &index(path, i));
+                }
+                true
+            }
+            ("string", Value::String(_)) | ("boolean", Value::Bool(_)) => true,
+            ("string", Value::Number(number)) => match format {
+                "int-or-string" => number.is_i64() || number.is_u64(),
+                "quantity" => true,
+                _ => false,
+            },
+            ("integer", Value::Number(number)) => number.is_i64() || number.is_u64(),
+            ("number", Value::Number(_)) => true,
+            ("", _) => true,
+            _ => false,
+        };
+
+        if !matches {
+            let expected = match format {
+                "int-or-string" => "integer or string",
+                "quantity" => "quantity",
+                _ => expected,
+            };
+            self.push(
+                ViolationKind::TypeMismatch,
+                path,
+                format!("expected {expected}, found {}", describe(value)),
+            );
+        }
+    }
+
+    fn walk_object(&mut self, schema: &Value, mapping: &Mapping, path: &str) {
+        let properties = schema["properties"].as_mapping();
+        let additional = &schema["additionalProperties"];
+        let free_form = schema["x-kubernetes-preserve-unknown-fields"] == true
+            || (properties.is_none() && additional.is_null());
+
+        for (key, value) in mapping {
+            let key = str_at(key);
+            let path = field(path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => self.walk(property, value, &path),
+                None if !additional.is_null() => self.walk(additional, value, &path),
+                None if free_form => {}
+                None => self.push(
+                    ViolationKind::UnknownField,
+                    path,
+                    format!(
+                        "unknown field in the Kubernetes {} schema subset",
+                        self.openapi.version
+                    ),
+                ),
+            }
+        }
+
+        for required in sequence(&schema["required"]) {
+            let required = str_at(required);
+            if mapping.get(required).is_none_or(Value::is_null) {
+                self.push(
+                    ViolationKind::MissingField,
+                    field(path, required),
+                    "required field is missing",
+                );
+            }
+        }
+    }
+}
+
+fn components(yaml: &str) -> OpenApiResult<Mapping> {
+    let document: Value = serde_yaml::from_str(yaml)?;
+    match &document["components"]["schemas"] {
+        Value::Mapping(schemas) => Ok(schemas.clone()),
+        _ => Ok(Mapping::default()),
+    }
+}
+
+/// Merges an overlay into a schema, adding properties and replacing anything else.
+fn merge(existing: &mut Value, overlay: Value) {
+    match (existing, overlay) {
+        (Value::Mapping(existing), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match existing.get_mut(&key) {
+                    Some(current) => merge(current, value),
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+        }
+        (existing, overlay) => *existing = overlay,
+    }
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Sequence(_) => "array",
+        Value::Mapping(_) => "object",
+        Value::Tagged(_) => "tagged value",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{prelude::*, recipes::*};
+
+    #[test]
+    fn recipes_match_every_version() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .with_resource(Resource::RabbitMQ(RabbitMQ::new("rabbitmq:3".into())))
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+
+        for version in KubernetesVersion::supported() {
+            let openapi = OpenApi::for_version(version)?;
+            assert_eq!(stack.check_openapi_subset(&openapi)?, Vec::default());
+        }
+        assert!(OpenApi::for_version("1.26".parse()?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_field_mistakes() -> anyhow::Result<()> {
+        let yaml = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: api
+  namespace: example
+spec:
+  replicas: two
+  template:
+    spec:
+      containers:
+      - name: api
+        image: example/api:v1
+        imagepullPolicy: Always
+        ports:
+        - containerPort: "8080"
+        lifecycle:
+          preStop:
+            sleep:
+              seconds: 5
+---
+apiVersion: batch/v1beta1
+kind: CronJob
+metadata:
+  name: cleanup
+---
+apiVersion: flowcontrol.apiserver.k8s.io/v1beta3
+kind: FlowSchema
+metadata:
+  name: example
+"#;
+        let objects: Vec<Value> = serde_yaml::Deserializer::from_str(yaml)
+            .map(Value::deserialize)
+            .collect::<Result<_, _>>()?;
+
+        let openapi = OpenApi::for_version("v1.28.4".parse()?)?;
+        let found: Vec<String> = openapi
+            .validate(&objects)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "Deployment example/api: spec.replicas: expected integer, found string",
+                "Deployment example/api: spec.template.spec.containers[0].imagepullPolicy: unknown field in the Kubernetes 1.28 schema subset",
+                "Deployment example/api: spec.template.spec.containers[0].ports[0].containerPort: expected integer, found string",
+                "Deployment example/api: spec.template.spec.containers[0].lifecycle.preStop.sleep: unknown field in the Kubernetes 1.28 schema subset",
+                "Deployment example/api: spec.selector: required field is missing",
+                "CronJob cleanup: apiVersion: batch/v1beta1 CronJob was removed in Kubernetes 1.25, use batch/v1",
+            ]
+        );
+
+        let unknown = openapi.validate(&objects);
+        assert!(unknown
+            .iter()
+            .filter(|v| v.kind() == ViolationKind::UnknownField)
+            .all(|v| !v.is_error()));
+
+        let openapi = OpenApi::for_version("1.29".parse()?)?;
+        let violations = openapi.validate(&objects);
+        assert!(!violations.iter().any(|v| v.path().ends_with("sleep")));
+        assert_eq!(
+            violations.last().map(SchemaViolation::kind),
+            Some(ViolationKind::DeprecatedApiVersion)
+        );
+
+        let volume = serde_yaml::to_value(Volume::config_map("config".into(), "api".into()))?;
+        assert_eq!(volume["configMap"]["name"], "api");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use serde_yaml::Value;
+
+use crate::check::str_at;
+
+/// Kinds in the order they can be applied without referring to something that doesn't exist
+/// yet. Kinds not listed, such as custom resources, come last so their definitions are in place.
+#[rustfmt::skip]
+pub const APPLY_ORDER: &[&str] = &[
+    // Namespaces and cluster-wide definitions
+    "Namespace",
+    "CustomResourceDefinition",
+    "PriorityClass",
+    "StorageClass",
+    "ResourceQuota",
+    "LimitRange",
+    // Identity and access
+    "ServiceAccount",
+    "ClusterRole",
+    "Role",
+    "ClusterRoleBinding",
+    "RoleBinding",
+    // Configuration
+    "Secret",
+    "ConfigMap",
+    // Storage
+    "PersistentVolume",
+    "PersistentVolumeClaim",
+    // Networking
+    "Service",
+    // Workloads
+    "Pod",
+    "ReplicaSet",
+    "Deployment",
+    "StatefulSet",
+    "DaemonSet",
+    "Job",
+    "CronJob",
+    // Everything that targets workloads
+    "HorizontalPodAutoscaler",
+    "PodDisruptionBudget",
+    "NetworkPolicy",
+    "IngressClass",
+    "Ingress",
+];
+
+/// Position of `kind` in [`APPLY_ORDER`], or past its end for unlisted kinds.
+pub fn rank(kind: &str) -> usize {
+    APPLY_ORDER
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(APPLY_ORDER.len())
+}
+
+/// Sorts objects into apply order, keeping the original order within a kind.
+pub fn sort_for_apply(objects: &mut [Value]) {
+    objects.sort_by_key(|object| rank(str_at(&object["kind"])));
+}
+
+/// Sorts objects into teardown order, the exact reverse of apply order.
+pub fn sort_for_teardown(objects: &mut [Value]) {
+    sort_for_apply(objects);
+    objects.reverse();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipes::*;
+
+    fn kinds(objects: &[Value]) -> Vec<&str> {
+        objects.iter().map(|o| str_at(&o["kind"])).collect()
+    }
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let mut objects: Vec<Value> = serde_yaml::from_str(
+            r#"
+            - {kind: Widget, metadata: {name: a}}
+            - {kind: Deployment, metadata: {name: web}}
+            - {kind: Service, metadata: {name: web}}
+            - {kind: Secret, metadata: {name: one}}
+            - {kind: Namespace, metadata: {name: ns}}
+            - {kind: Secret, metadata: {name: two}}
+            - {kind: CustomResourceDefinition, metadata: {name: widgets}}
+            "#,
+        )?;
+
+        sort_for_apply(&mut objects);
+        assert_eq!(
+            kinds(&objects),
+            vec![
+                "Namespace",
+                "CustomResourceDefinition",
+                "Secret",
+                "Secret",
+                "Service",
+                "Deployment",
+                "Widget"
+            ]
+        );
+        assert_eq!(objects[2]["metadata"]["name"], "one");
+
+        sort_for_teardown(&mut objects);
+        assert_eq!(kinds(&objects)[0], "Widget");
+        assert_eq!(objects[3]["metadata"]["name"], "two");
+
+        let stack = Stack::builder("example", true, Environment::Production)
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .with_resource(Resource::PosgreSQL(PostgreSQL::new("postgres:16".into())))
+            .build();
+        assert_eq!(
+            kinds(&stack.as_k8s()?),
+            vec![
+                "Namespace",
+                "Service",
+                "Service",
+                "Deployment",
+                "StatefulSet"
+            ]
+        );
+        assert_eq!(
+            kinds(&stack.as_k8s_for_teardown()?),
+            vec![
+                "StatefulSet",
+                "Deployment",
+                "Service",
+                "Service",
+                "Namespace"
+            ]
+        );
+
+        Ok(())
+    }
+}
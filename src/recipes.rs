@@ -1,7 +1,24 @@
-use std::cell::Cell;
-
-use crate::prelude::*;
+use std::{fmt, io::Write, path::Path};
+
+use crate::{
+    capacity::{CapacityError, Estimate},
+    check::{self, DanglingReference},
+    compose::Compose,
+    diff::{self, Diff},
+    directory::{DirectoryError, DirectoryReport, DirectoryWriter},
+    graph::Graph,
+    helm::{HelmChart, HelmError, HelmExporter},
+    json::{self, JsonFormat},
+    lint::{self, LintReport, Linter},
+    manifest::{Manifest, ManifestError},
+    openapi::{OpenApi, SchemaViolation},
+    order,
+    prelude::*,
+    report::Report,
+    yaml::{YamlError, YamlWriter},
+};
 use derive_more::Constructor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_yaml::Value;
 use thiserror::Error;
 
@@ -9,62 +26,215 @@ use thiserror::Error;
 pub enum StackError {
     #[error("SerdeYamlError: {0}")]
     SerdeYamlError(#[from] serde_yaml::Error),
+    #[error("YamlError: {0}")]
+    YamlError(#[from] YamlError),
+    #[error("DirectoryError: {0}")]
+    DirectoryError(#[from] DirectoryError),
+    #[error("ManifestError: {0}")]
+    ManifestError(#[from] ManifestError),
+    #[error("CapacityError: {0}")]
+    CapacityError(#[from] CapacityError),
+    #[error("HelmError: {0}")]
+    HelmError(#[from] HelmError),
+    #[error("SerdeJsonError: {0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("ImageRefError: {0}")]
+    ImageRefError(#[from] ImageRefError),
+    #[error("ImagePolicyError: {0}")]
+    ImagePolicyError(String),
+    #[error("ValidationError: {0}")]
+    ValidationError(#[from] ValidationErrors),
+    #[error("DefinitionError: {path}: {message}")]
+    DefinitionError {
+        path: String,
+        location: Option<(usize, usize)>,
+        message: String,
+    },
 }
 
 pub type StackResult<T> = Result<T, StackError>;
 
-#[derive(Debug, Constructor)]
+/// A stack definition. Serialized as a mapping with `name`, `environment` (`production` or
+/// `{ephemeral: <name>}`), `createNamespace`, `imagePolicy` and `resources`, where each resource
+/// is tagged by `type`.
+#[derive(Debug, Clone, Constructor, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Stack {
+    #[serde(rename = "name")]
     namespace: String,
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
     environment: Environment,
+    #[serde(default)]
     resources: Vec<Resource>,
+    #[serde(default = "default_true")]
     create_namespace: bool,
+    #[serde(default)]
+    image_policy: ImagePolicy,
+}
+
+/// Stack-wide rules applied to every image a recipe renders.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ImagePolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    registry_rewrites: Vec<RegistryRewrite>,
+    #[serde(default)]
+    forbid_latest_in_production: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistryRewrite {
+    from: String,
+    to: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Environment {
+    #[default]
     Production,
     Ephemeral(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
 pub enum Resource {
+    #[serde(rename = "postgresql")]
     PosgreSQL(PostgreSQL),
     RabbitMQ(RabbitMQ),
     Nginx(Nginx),
     Microservice(Microservice),
+    /// Any other object, such as a [`CustomResource`], rendered as-is. Serialized with the
+    /// object under `manifest`, since its own fields may include `type`.
+    Object(#[serde(with = "object")] Value),
+}
+
+mod object {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Object<T> {
+        manifest: T,
+    }
+
+    pub fn serialize<S: Serializer>(manifest: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+        Object { manifest }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        Ok(Object::deserialize(deserializer)?.manifest)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_replicas() -> u32 {
+    1
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Environment::Production => write!(f, "production"),
+            Environment::Ephemeral(name) => write!(f, "ephemeral ({name})"),
+        }
+    }
 }
 
-#[derive(Debug, Constructor)]
+impl Resource {
+    pub fn object<T: Serialize>(object: &T) -> StackResult<Resource> {
+        Ok(Resource::Object(serde_yaml::to_value(object)?))
+    }
+}
+
+#[derive(Debug, Clone, Constructor, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PostgreSQL {
     image: String,
 }
 
-#[derive(Debug, Constructor)]
+#[derive(Debug, Clone, Constructor, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct RabbitMQ {
     image: String,
 }
 
-#[derive(Debug, Constructor)]
+#[derive(Debug, Clone, Constructor, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Microservice {
     image: String,
     version: String,
-    replicas: u16,
+    #[serde(default = "default_replicas")]
+    replicas: u32,
     role: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     env: Vec<EnvironmentVariable>,
+    #[serde(rename = "ports", default, skip_serializing_if = "Vec::is_empty")]
     tcp_ports: Vec<MicroservicePort>,
 }
 
-#[derive(Debug)]
+/// Serialized as `{port, name, protocol}`, where `protocol` is `tcp` (the default) or `udp`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "PortDefinition", into = "PortDefinition")]
 pub enum MicroservicePort {
     TCP { port: u16, name: Option<String> },
     UDP { port: u16, name: Option<String> },
 }
 
-#[derive(Debug, Constructor)]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PortDefinition {
+    port: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default)]
+    protocol: Protocol,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl From<PortDefinition> for MicroservicePort {
+    fn from(PortDefinition { port, name, protocol }: PortDefinition) -> Self {
+        match protocol {
+            Protocol::Tcp => MicroservicePort::TCP { port, name },
+            Protocol::Udp => MicroservicePort::UDP { port, name },
+        }
+    }
+}
+
+impl From<MicroservicePort> for PortDefinition {
+    fn from(port: MicroservicePort) -> Self {
+        match port {
+            MicroservicePort::TCP { port, name } => PortDefinition {
+                port,
+                name,
+                protocol: Protocol::Tcp,
+            },
+            MicroservicePort::UDP { port, name } => PortDefinition {
+                port,
+                name,
+                protocol: Protocol::Udp,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Constructor, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Nginx {
     image: String,
-    replicas: u16,
+    #[serde(default = "default_replicas")]
+    replicas: u32,
 }
 
 impl Stack {
@@ -72,15 +242,98 @@ impl Stack {
         name: S,
         create_namespace: bool,
         environment: Environment,
-    ) -> Cell<Stack> {
-        Cell::new(Stack {
-            namespace: name.into(),
-            environment,
-            resources: Vec::default(),
-            create_namespace,
+    ) -> StackBuilder {
+        StackBuilder {
+            stack: Stack {
+                namespace: name.into(),
+                environment,
+                resources: Vec::default(),
+                create_namespace,
+                image_policy: ImagePolicy::default(),
+            },
+        }
+    }
+
+    /// Reads a stack definition from YAML. Errors name the path of the bad field and, where
+    /// known, its line and column.
+    pub fn from_yaml(definition: &str) -> StackResult<Stack> {
+        serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(definition)).map_err(
+            |error| {
+                let path = error.path().to_string();
+                let error = error.into_inner();
+                StackError::DefinitionError {
+                    path,
+                    location: error.location().map(|l| (l.line(), l.column())),
+                    message: error.to_string(),
+                }
+            },
+        )
+    }
+
+    /// Reads a stack definition from TOML, in the same format as [`Stack::from_yaml`].
+    pub fn from_toml(definition: &str) -> StackResult<Stack> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(definition)).map_err(|error| {
+            let path = error.path().to_string();
+            let error = error.into_inner();
+            let location = error.span().map(|span| {
+                let before = &definition[..span.start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                (line, column)
+            });
+            let message = match location {
+                Some((line, column)) => {
+                    format!("{} at line {line} column {column}", error.message())
+                }
+                None => error.message().to_string(),
+            };
+            StackError::DefinitionError {
+                path,
+                location,
+                message,
+            }
         })
     }
 
+    /// Returns the stack for another environment, e.g. to render a production definition as a
+    /// preview.
+    pub fn with_environment(mut self, environment: Environment) -> Stack {
+        self.environment = environment;
+        self
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    fn image(&self, image: &str) -> StackResult<String> {
+        self.image_policy.apply(image, &self.environment)
+    }
+
+    /// Applies the image policy to the containers of a raw object's pod template, if it has one.
+    fn object(&self, object: &Value) -> StackResult<Value> {
+        let mut object = object.clone();
+        let Some((path, _)) = lint::pod_spec(&object) else {
+            return Ok(object);
+        };
+        if let Some(pod) = path
+            .split('.')
+            .try_fold(&mut object, |value, key| value.get_mut(key))
+        {
+            for kind in ["initContainers", "containers"] {
+                let Some(Value::Sequence(containers)) = pod.get_mut(kind) else {
+                    continue;
+                };
+                for container in containers {
+                    if let Some(Value::String(image)) = container.get_mut("image") {
+                        *image = self.image(image)?;
+                    }
+                }
+            }
+        }
+        Ok(object)
+    }
+
     fn namespace(&self) -> Vec<String> {
         match &self.environment {
             Environment::Production => vec![self.namespace.clone()],
@@ -96,51 +349,55 @@ impl Stack {
 
         let metadata = Metadata::builder(app_name.clone(), ns.clone())
             .with_label("app", &app_name)
-            .build();
+            .build()?;
 
-        let stateful_set = StatefulSet::new(
+        let stateful_set = StatefulSet::try_new(
             metadata.clone(),
             StatefulSetSpec::builder(
                 service_name.clone(),
                 Selector::builder()
                     .with_match_label("app", &app_name)
-                    .build(),
+                    .build()?,
                 StatefulSetSpecTemplate::new(
                     metadata.clone(),
                     StatefulSetSpecTemplateSpec::builder()
                         .with_container(
-                            Container::builder(pg.image.clone(), app_name.clone(), Vec::default())
-                                .with_port(ContainerPort::tcp(5432))
-                                .with_env(("POSTGRES_PASSWORD", "postgres").into())
-                                .with_volume_mount(VolumeMount::new(
-                                    volume_name.clone(),
-                                    "/var/lib/postgresql/data".to_string(),
-                                ))
-                                .build(),
+                            Container::builder(
+                                self.image(&pg.image)?,
+                                app_name.clone(),
+                                Vec::default(),
+                            )
+                            .with_port(ContainerPort::tcp(5432))
+                            .with_env(("POSTGRES_PASSWORD", "postgres").into())
+                            .with_volume_mount(VolumeMount::new(
+                                volume_name.clone(),
+                                "/var/lib/postgresql/data".to_string(),
+                            ))
+                            .build()?,
                         )
-                        .build(),
+                        .build()?,
                 ),
             )
             .with_volume_claim_template(VolumeClaimTemplate::new(
-                Metadata::builder(volume_name.clone(), ns.clone()).build(),
+                Metadata::builder(volume_name.clone(), ns.clone()).build()?,
                 VolumeClaimTemplateSpec::builder(VolumeClaimTemplateSpec::storage_resources(
                     "5Gi".to_string(),
                 ))
                 .with_access_mode(VolumeClaimAccessMode::ReadWriteOnce)
-                .build(),
+                .build()?,
             ))
-            .build(),
-        );
+            .build()?,
+        )?;
 
-        let service = Service::new(
+        let service = Service::try_new(
             Metadata::builder(service_name.clone(), ns.clone())
                 .with_label("app", &app_name.clone())
-                .build(),
+                .build()?,
             ServiceSpec::builder(ServiceType::ClusterIP)
                 .with_selector("app", &app_name)
                 .with_port(5432, 5432, Some("db"))
-                .build(),
-        );
+                .build()?,
+        )?;
 
         Ok(vec![
             serde_yaml::to_value(&stateful_set)?,
@@ -156,52 +413,56 @@ impl Stack {
 
         let metadata = Metadata::builder(app_name.clone(), ns.clone())
             .with_label("app", &app_name)
-            .build();
+            .build()?;
 
-        let stateful_set = StatefulSet::new(
+        let stateful_set = StatefulSet::try_new(
             metadata.clone(),
             StatefulSetSpec::builder(
                 service_name.clone(),
                 Selector::builder()
                     .with_match_label("app", &app_name)
-                    .build(),
+                    .build()?,
                 StatefulSetSpecTemplate::new(
                     metadata.clone(),
                     StatefulSetSpecTemplateSpec::builder()
                         .with_container(
-                            Container::builder(rmq.image.clone(), app_name.clone(), Vec::default())
-                                .with_port(ContainerPort::tcp(5672))
-                                .with_port(ContainerPort::tcp(15672))
-                                .with_volume_mount(VolumeMount::new(
-                                    volume_name.clone(),
-                                    "/var/lib/postgresql/data".to_string(),
-                                ))
-                                .build(),
+                            Container::builder(
+                                self.image(&rmq.image)?,
+                                app_name.clone(),
+                                Vec::default(),
+                            )
+                            .with_port(ContainerPort::tcp(5672))
+                            .with_port(ContainerPort::tcp(15672))
+                            .with_volume_mount(VolumeMount::new(
+                                volume_name.clone(),
+                                "/var/lib/postgresql/data".to_string(),
+                            ))
+                            .build()?,
                         )
-                        .build(),
+                        .build()?,
                 ),
             )
             .with_volume_claim_template(VolumeClaimTemplate::new(
-                Metadata::builder(volume_name.clone(), ns.clone()).build(),
+                Metadata::builder(volume_name.clone(), ns.clone()).build()?,
                 VolumeClaimTemplateSpec::builder(VolumeClaimTemplateSpec::storage_resources(
                     "5Gi".to_string(),
                 ))
                 .with_access_mode(VolumeClaimAccessMode::ReadWriteOnce)
-                .build(),
+                .build()?,
             ))
-            .build(),
-        );
+            .build()?,
+        )?;
 
-        let service = Service::new(
+        let service = Service::try_new(
             Metadata::builder(service_name.clone(), ns.clone())
                 .with_label("app", &app_name.clone())
-                .build(),
+                .build()?,
             ServiceSpec::builder(ServiceType::ClusterIP)
                 .with_selector("app", &app_name)
                 .with_port(5672, 5672, Some("amqp"))
                 .with_port(15672, 15672, Some("web"))
-                .build(),
-        );
+                .build()?,
+        )?;
 
         Ok(vec![
             serde_yaml::to_value(&stateful_set)?,
@@ -216,38 +477,42 @@ impl Stack {
 
         let metadata = Metadata::builder(app_name.clone(), ns.clone())
             .with_label("app", &app_name)
-            .build();
-        let deployment = Deployment::new(
+            .build()?;
+        let deployment = Deployment::try_new(
             metadata.clone(),
             DeploymentSpec::new(
                 nginx.replicas,
                 Selector::builder()
                     .with_match_label("app", &app_name)
-                    .build(),
+                    .build()?,
                 DeploymentTemplate::new(
                     DeploymentTemplateMetadata::builder(ns.clone())
                         .with_label("app", &app_name)
-                        .build(),
+                        .build()?,
                     DeploymentTemplateSpec::builder()
                         .with_container(
-                            Container::builder(&nginx.image, &app_name, Vec::default())
-                                .with_port(ContainerPort::tcp(80))
-                                .build(),
+                            Container::builder(
+                                self.image(&nginx.image)?,
+                                app_name.clone(),
+                                Vec::default(),
+                            )
+                            .with_port(ContainerPort::tcp(80))
+                            .build()?,
                         )
-                        .build(),
+                        .build()?,
                 ),
             ),
-        );
+        )?;
 
-        let service = Service::new(
+        let service = Service::try_new(
             Metadata::builder(service_name.clone(), ns.clone())
                 .with_label("app", &app_name)
-                .build(),
+                .build()?,
             ServiceSpec::builder(ServiceType::LoadBalancer)
                 .with_selector("app", &app_name)
                 .with_port(80, 80, Some("web"))
-                .build(),
-        );
+                .build()?,
+        )?;
 
         Ok(vec![
             serde_yaml::to_value(&deployment)?,
@@ -264,50 +529,47 @@ impl Stack {
             .with_label("app", &app_name)
             .with_label("role", &microservice.role)
             .with_label("version", &microservice.version)
-            .build();
-        let deployment = Deployment::new(
+            .build()?;
+        let deployment = Deployment::try_new(
             metadata.clone(),
             DeploymentSpec::new(
                 microservice.replicas,
                 Selector::builder()
                     .with_match_label("app", &app_name)
-                    .build(),
+                    .build()?,
                 DeploymentTemplate::new(
                     DeploymentTemplateMetadata::builder(ns.clone())
                         .with_label("app", &app_name)
-                        .build(),
+                        .build()?,
                     DeploymentTemplateSpec::builder()
-                        .with_container({
-                            let mut c =
-                                Container::builder(&microservice.image, &app_name, Vec::default());
-                            for port in &microservice.tcp_ports {
-                                let port = match port {
+                        .with_container(
+                            Container::builder(
+                                self.image(&microservice.image)?,
+                                app_name.clone(),
+                                Vec::default(),
+                            )
+                            .with_each(&microservice.tcp_ports, |c, port| {
+                                c.with_port(match port {
                                     MicroservicePort::TCP { port, .. } => ContainerPort::tcp(*port),
                                     MicroservicePort::UDP { port, .. } => ContainerPort::udp(*port),
-                                };
-
-                                c = c.with_port(port);
-                            }
-
-                            for environment_var in microservice.env.iter() {
-                                c = c.with_env(environment_var.clone())
-                            }
-
-                            c.build()
-                        })
-                        .build(),
+                                })
+                            })
+                            .with_each(microservice.env.iter().cloned(), ContainerBuilder::with_env)
+                            .build()?,
+                        )
+                        .build()?,
                 ),
             ),
-        );
+        )?;
 
         let mut values = Vec::default();
         values.push(serde_yaml::to_value(&deployment)?);
 
         if !microservice.tcp_ports.is_empty() {
-            let service = Service::new(
+            let service = Service::try_new(
                 Metadata::builder(service_name.clone(), ns.clone())
                     .with_label("app", &app_name)
-                    .build(),
+                    .build()?,
                 {
                     let mut spec = ServiceSpec::builder(ServiceType::LoadBalancer)
                         .with_selector("app", &app_name);
@@ -318,16 +580,30 @@ impl Stack {
                         };
                         spec = spec.with_port(port, port, name);
                     }
-                    spec.build()
+                    spec.build()?
                 },
-            );
+            )?;
             values.push(serde_yaml::to_value(&service)?)
         }
 
         Ok(values)
     }
 
+    /// Renders every resource, sorted into an order that `kubectl apply` can follow.
     pub fn as_k8s(&self) -> StackResult<Vec<Value>> {
+        let mut values = self.render()?;
+        order::sort_for_apply(&mut values);
+        Ok(values)
+    }
+
+    /// Renders every resource in the reverse of apply order, for deleting the stack.
+    pub fn as_k8s_for_teardown(&self) -> StackResult<Vec<Value>> {
+        let mut values = self.render()?;
+        order::sort_for_teardown(&mut values);
+        Ok(values)
+    }
+
+    fn render(&self) -> StackResult<Vec<Value>> {
         let mut values = Vec::default();
         if self.create_namespace {
             let ns = serde_yaml::to_value(Namespace::new(self.namespace().join("-")))?;
@@ -340,29 +616,310 @@ impl Stack {
                 Resource::RabbitMQ(rmq) => self.rabbitmq(rmq)?,
                 Resource::Nginx(nginx) => self.nginx(nginx)?,
                 Resource::Microservice(ms) => self.microservice(ms)?,
+                Resource::Object(object) => vec![self.object(object)?],
             };
             vs.append(&mut v);
             Ok(vs)
         })
     }
+
+    /// Checks the rendered objects for references that don't resolve within the stack.
+    pub fn check(&self) -> StackResult<Vec<DanglingReference>> {
+        Ok(check::check(&self.as_k8s()?))
+    }
+
+    /// Checks the rendered objects against the bundled schema subset of a Kubernetes version,
+    /// on a best-effort basis, see [`OpenApi`].
+    pub fn check_openapi_subset(&self, openapi: &OpenApi) -> StackResult<Vec<SchemaViolation>> {
+        Ok(openapi.validate(&self.as_k8s()?))
+    }
+
+    pub fn lint(&self, linter: &Linter) -> StackResult<LintReport> {
+        Ok(linter.lint(&self.as_k8s()?, &self.environment))
+    }
+
+    /// What changes when going from this stack to `other`.
+    pub fn diff(&self, other: &Stack) -> StackResult<Diff> {
+        Ok(diff::diff(&self.as_k8s()?, &other.as_k8s()?))
+    }
+
+    /// What changes when going from the manifests below `dir`, such as a previous
+    /// [`write_dir`](Stack::write_dir), to this stack. Kustomizations are skipped.
+    pub fn diff_dir<P: AsRef<Path>>(&self, dir: P) -> StackResult<Diff> {
+        let current: Vec<Value> = Manifest::load_dir_values(dir)?
+            .into_iter()
+            .filter(|value| value["kind"] != "Kustomization")
+            .collect();
+        Ok(diff::diff(&current, &self.as_k8s()?))
+    }
+
+    /// Writes the rendered objects as a multi-document YAML stream, headed by a comment naming
+    /// the stack and environment.
+    pub fn write_yaml<W: Write>(&self, writer: W) -> StackResult<()> {
+        let mut writer = YamlWriter::new(writer)
+            .with_header_line("Generated by flagship")
+            .with_header_line(format!("Stack: {}", self.namespace))
+            .with_header_line(format!("Environment: {}", self.environment))
+            .with_leading_separator(true);
+        writer.write_all(&self.as_k8s()?)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes the rendered objects below the writer's directory, one file per object.
+    pub fn write_dir(&self, writer: &DirectoryWriter) -> StackResult<DirectoryReport> {
+        Ok(writer.write(&self.as_k8s()?)?)
+    }
+
+    /// Relations between the rendered objects, for DOT or Mermaid diagrams.
+    pub fn graph(&self) -> StackResult<Graph> {
+        Ok(Graph::from_objects(&self.as_k8s()?))
+    }
+
+    /// Estimates the CPU, memory and storage the stack reserves.
+    pub fn estimate(&self) -> StackResult<Estimate> {
+        Ok(Estimate::from_objects(&self.as_k8s()?)?)
+    }
+
+    /// Describes the stack for people who don't read manifests.
+    pub fn report(&self) -> StackResult<Report> {
+        Ok(Report::new(
+            format!("Stack {}", self.namespace),
+            self.environment.to_string(),
+            &self.as_k8s()?,
+        ))
+    }
+
+    pub fn as_compose(&self) -> StackResult<Compose> {
+        Ok(Compose::from_objects(&self.as_k8s()?))
+    }
+
+    pub fn as_helm_chart(&self, exporter: &HelmExporter) -> StackResult<HelmChart> {
+        Ok(exporter.export(&self.as_k8s()?)?)
+    }
+
+    pub fn as_json(&self, format: JsonFormat) -> StackResult<String> {
+        Ok(json::to_string(&self.as_k8s()?, format)?)
+    }
 }
 
-pub trait StackBuilder {
-    fn with_resource(self, resource: Resource) -> Self;
-    fn build(self) -> Stack;
+impl ImagePolicy {
+    pub fn builder() -> ImagePolicyBuilder {
+        ImagePolicyBuilder {
+            policy: ImagePolicy::default(),
+        }
+    }
+
+    /// Rewrites the registry of `image` if a rule matches and checks it against the
+    /// environment's restrictions. Registries are matched in normalized form, so Docker Hub
+    /// images are matched by `docker.io`, but images no rule matches are kept as written.
+    /// Images that can't be parsed, such as ones with template placeholders, are passed through.
+    pub fn apply(&self, image: &str, environment: &Environment) -> StackResult<String> {
+        let Ok(parsed) = ImageRef::parse(image) else {
+            return Ok(image.to_string());
+        };
+
+        let rewrite = self
+            .registry_rewrites
+            .iter()
+            .find(|rewrite| rewrite.from == parsed.registry());
+
+        if self.forbid_latest_in_production
+            && matches!(environment, Environment::Production)
+            && !parsed.is_pinned()
+        {
+            return Err(StackError::ImagePolicyError(format!(
+                "image {image} must be pinned to a tag other than latest or a digest in production"
+            )));
+        }
+
+        Ok(match rewrite {
+            Some(RegistryRewrite { to, .. }) => parsed.with_registry(to).to_string(),
+            None => image.to_string(),
+        })
+    }
 }
 
-impl StackBuilder for Cell<Stack> {
-    fn with_resource(self, resource: Resource) -> Self {
-        let mut stack = self.into_inner();
-        stack.resources.push(resource);
-        Cell::new(stack)
+#[derive(Debug, Clone)]
+pub struct ImagePolicyBuilder {
+    policy: ImagePolicy,
+}
+
+impl ImagePolicyBuilder {
+    pub fn with_registry_rewrite<S: Into<String>>(mut self, from: S, to: S) -> Self {
+        self.policy.registry_rewrites.push(RegistryRewrite {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    pub fn forbid_latest_in_production(mut self) -> Self {
+        self.policy.forbid_latest_in_production = true;
+        self
+    }
+
+    pub fn build(self) -> ImagePolicy {
+        self.policy
+    }
+}
+
+impl Builder for ImagePolicyBuilder {}
+
+#[derive(Debug, Clone)]
+pub struct StackBuilder {
+    stack: Stack,
+}
+
+impl StackBuilder {
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.stack.resources.push(resource);
+        self
     }
 
-    fn build(self) -> Stack {
-        self.into_inner()
+    pub fn with_image_policy(mut self, image_policy: ImagePolicy) -> Self {
+        self.stack.image_policy = image_policy;
+        self
+    }
+
+    pub fn build(self) -> Stack {
+        self.stack
     }
 }
 
+impl Builder for StackBuilder {}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_policy() -> anyhow::Result<()> {
+        let policy = ImagePolicy::builder()
+            .with_registry_rewrite("docker.io", "mirror.example.com/hub")
+            .forbid_latest_in_production()
+            .build();
+
+        let stack = Stack::builder("example", false, Environment::Ephemeral("dev".into()))
+            .with_image_policy(policy)
+            .with_resource(Resource::Nginx(Nginx::new("nginx".into(), 1)))
+            .build();
+        let values = stack.as_k8s()?;
+        assert_eq!(
+            values[1]["spec"]["template"]["spec"]["containers"][0]["image"].as_str(),
+            Some("mirror.example.com/hub/library/nginx")
+        );
+
+        let stack = Stack::builder("example", false, Environment::Production)
+            .with_image_policy(ImagePolicy::builder().forbid_latest_in_production().build())
+            .with_resource(Resource::Nginx(Nginx::new("nginx:latest".into(), 1)))
+            .build();
+        assert!(matches!(
+            stack.as_k8s(),
+            Err(StackError::ImagePolicyError(_))
+        ));
+
+        let policy = ImagePolicy::builder()
+            .with_registry_rewrite("docker.io", "mirror.example.com/hub")
+            .build();
+        assert_eq!(
+            policy.apply("nginx:1.27", &Environment::Production)?,
+            "mirror.example.com/hub/library/nginx:1.27"
+        );
+        assert_eq!(
+            policy.apply("ghcr.io/example/api:1.0", &Environment::Production)?,
+            "ghcr.io/example/api:1.0"
+        );
+        assert_eq!(
+            policy.apply("{{ .Values.image }}", &Environment::Production)?,
+            "{{ .Values.image }}"
+        );
+
+        let job: Value = serde_yaml::from_str(
+            "{apiVersion: batch/v1, kind: Job, metadata: {name: migrate}, \
+             spec: {template: {spec: {containers: [{name: migrate, image: example/migrate}]}}}}",
+        )?;
+        let stack = Stack::builder("example", false, Environment::Production)
+            .with_image_policy(ImagePolicy::builder().forbid_latest_in_production().build())
+            .with_resource(Resource::Object(job))
+            .build();
+        assert!(matches!(
+            stack.as_k8s(),
+            Err(StackError::ImagePolicyError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn definitions() -> anyhow::Result<()> {
+        let stack = Stack::from_yaml(
+            r#"
+name: example
+environment: {ephemeral: dev}
+imagePolicy:
+  registryRewrites: [{from: docker.io, to: mirror.example.com/hub}]
+resources:
+  - type: postgresql
+    image: postgres:16
+  - type: microservice
+    image: ghcr.io/example/api:1.0
+    version: "1.0"
+    role: api
+    env: [{name: LOG_LEVEL, value: debug}]
+    ports: [{port: 8080, name: http}, {port: 5353, name: dns, protocol: udp}]
+  - type: object
+    manifest: {apiVersion: v1, kind: ConfigMap, metadata: {name: extra}}
+"#,
+        )?;
+        let rendered = stack.as_k8s()?;
+        assert_eq!(rendered[0]["metadata"]["name"], "example-dev");
+        assert!(rendered.iter().any(|o| o["metadata"]["name"] == "extra"));
+
+        let yaml = serde_yaml::to_string(&stack)?;
+        assert_eq!(Stack::from_yaml(&yaml)?.as_k8s()?, rendered);
+        assert!(yaml.contains("createNamespace: true"), "{yaml}");
+
+        let toml = Stack::from_toml(
+            r#"
+name = "example"
+environment = { ephemeral = "dev" }
+imagePolicy = { registryRewrites = [{ from = "docker.io", to = "mirror.example.com/hub" }] }
+
+[[resources]]
+type = "postgresql"
+image = "postgres:16"
+
+[[resources]]
+type = "microservice"
+image = "ghcr.io/example/api:1.0"
+version = "1.0"
+role = "api"
+env = [{ name = "LOG_LEVEL", value = "debug" }]
+ports = [{ port = 8080, name = "http" }, { port = 5353, name = "dns", protocol = "udp" }]
+
+[[resources]]
+type = "object"
+manifest = { apiVersion = "v1", kind = "ConfigMap", metadata = { name = "extra" } }
+"#,
+        )?;
+        assert_eq!(toml.as_k8s()?, rendered);
+
+        match Stack::from_yaml("name: example\nenvironment: staging\n") {
+            Err(StackError::DefinitionError { path, location, .. }) => {
+                assert_eq!(path, "environment");
+                assert_eq!(location, Some((2, 14)));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        match Stack::from_toml("name = \"example\"\ncreateNamespace = \"yes\"\n") {
+            Err(StackError::DefinitionError { path, location, .. }) => {
+                assert_eq!(path, "createNamespace");
+                assert_eq!(location, Some((2, 19)));
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        Ok(())
+    }
+}
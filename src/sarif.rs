@@ -0,0 +1,163 @@
+use serde_json::json;
+
+use crate::lint::{Finding, Rule, Severity, REFERENCE_RULE, VALIDATION_RULE};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF 2.1.0 log with a single run, the format code-scanning tools and merge request
+/// annotations read. Findings are located by file and line; objects rendered from a stack
+/// definition all point at the definition.
+#[derive(Debug, Clone)]
+pub struct SarifLog {
+    tool: String,
+    version: String,
+    rules: Vec<(String, String)>,
+    results: Vec<serde_json::Value>,
+}
+
+impl SarifLog {
+    /// An empty log that already describes the rules of [`crate::lint::validate`].
+    pub fn new<S: Into<String>>(tool: S, version: S) -> Self {
+        SarifLog {
+            tool: tool.into(),
+            version: version.into(),
+            rules: Vec::default(),
+            results: Vec::default(),
+        }
+        .with_rule(VALIDATION_RULE, "Fields the API server would reject.")
+        .with_rule(
+            REFERENCE_RULE,
+            "References between objects that don't resolve.",
+        )
+    }
+
+    pub fn with_rule<I: Into<String>, D: Into<String>>(mut self, id: I, description: D) -> Self {
+        let id = id.into();
+        if !self.rules.iter().any(|(known, _)| *known == id) {
+            self.rules.push((id, description.into()));
+        }
+        self
+    }
+
+    /// Describes every rule of a linter, e.g. `linter.rules()`.
+    pub fn with_rules<'a, I: IntoIterator<Item = &'a dyn Rule>>(self, rules: I) -> Self {
+        rules.into_iter().fold(self, |log, rule| {
+            log.with_rule(rule.id(), rule.description())
+        })
+    }
+
+    /// Records `finding` at `line` (1-based) of the file at `uri`, relative to the repository.
+    pub fn push(&mut self, finding: &Finding, uri: &str, line: usize) {
+        let level = match finding.severity() {
+            Severity::Info => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let object = finding.object();
+        let message = if finding.path().is_empty() {
+            format!("{object}: {}", finding.message())
+        } else {
+            format!("{object}: {}: {}", finding.path(), finding.message())
+        };
+        self.results.push(json!({
+            "ruleId": finding.rule(),
+            "level": level,
+            "message": { "text": message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": uri },
+                    "region": { "startLine": line.max(1) },
+                },
+                "logicalLocations": [{
+                    "fullyQualifiedName": format!(
+                        "{}/{}/{}",
+                        object.kind(),
+                        object.namespace(),
+                        object.name()
+                    ),
+                    "kind": "object",
+                }],
+            }],
+        }));
+    }
+
+    pub fn results(&self) -> &[serde_json::Value] {
+        &self.results
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|(id, description)| {
+                json!({ "id": id, "shortDescription": { "text": description } })
+            })
+            .collect();
+        json!({
+            "$schema": SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": self.tool,
+                        "version": self.version,
+                        "rules": rules,
+                    },
+                },
+                "results": self.results,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lint::Linter, recipes::Environment};
+
+    #[test]
+    fn usage() -> anyhow::Result<()> {
+        let objects: Vec<serde_yaml::Value> = serde_yaml::from_str(
+            r#"
+- apiVersion: v1
+  kind: Service
+  metadata: {name: web, namespace: example}
+  spec:
+    selector: {app: missing}
+    ports: [{port: 80, targetPort: 80}]
+"#,
+        )?;
+        let linter = Linter::default();
+        let mut log = SarifLog::new("flagship", "0.1.0").with_rules(linter.rules());
+        let findings = crate::lint::validate(&objects);
+        for finding in &findings {
+            log.push(finding, "manifests/web.yaml", 3);
+        }
+        for finding in linter.lint(&objects, &Environment::Production).findings() {
+            log.push(finding, "manifests/web.yaml", 3);
+        }
+
+        let sarif = log.to_json();
+        assert_eq!(sarif["version"], "2.1.0");
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], REFERENCE_RULE);
+        assert_eq!(result["level"], "error");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            3
+        );
+        assert_eq!(
+            result["locations"][0]["logicalLocations"][0]["fullyQualifiedName"],
+            "Service/example/web"
+        );
+        let rules = sarif["runs"][0]["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap();
+        assert!(log
+            .results()
+            .iter()
+            .all(|result| rules.iter().any(|rule| rule["id"] == result["ruleId"])));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum YamlError {
+    #[error("IoError: {0}")]
+    IoError(#[from] io::Error),
+    #[error("SerdeYamlError: {0}")]
+    SerdeYamlError(#[from] serde_yaml::Error),
+}
+
+pub type YamlResult<T> = Result<T, YamlError>;
+
+/// Streams objects to `W` as a multi-document YAML stream that
+/// [`Manifest::parse_all`](crate::manifest::Manifest::parse_all) reads back.
+#[derive(Debug)]
+pub struct YamlWriter<W: Write> {
+    writer: W,
+    header: Vec<String>,
+    leading_separator: bool,
+    documents: usize,
+}
+
+impl<W: Write> YamlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        YamlWriter {
+            writer,
+            header: Vec::default(),
+            leading_separator: false,
+            documents: 0,
+        }
+    }
+
+    /// Adds a line to the comment written before the first document.
+    pub fn with_header_line<S: Into<String>>(mut self, line: S) -> Self {
+        self.header.push(line.into());
+        self
+    }
+
+    /// Starts the stream with `---`, which some tools expect when concatenating streams.
+    pub fn with_leading_separator(mut self, leading_separator: bool) -> Self {
+        self.leading_separator = leading_separator;
+        self
+    }
+
+    pub fn write<T: Serialize>(&mut self, object: &T) -> YamlResult<()> {
+        if self.documents == 0 {
+            for line in &self.header {
+                writeln!(self.writer, "# {line}")?;
+            }
+        }
+        if self.documents > 0 || self.leading_separator {
+            writeln!(self.writer, "---")?;
+        }
+
+        serde_yaml::to_writer(&mut self.writer, object)?;
+        self.documents += 1;
+        Ok(())
+    }
+
+    pub fn write_all<T: Serialize>(&mut self, objects: &[T]) -> YamlResult<()> {
+        objects.iter().try_for_each(|object| self.write(object))
+    }
+
+    /// Number of documents written so far.
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> YamlResult<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Renders `objects` as a multi-document YAML string.
+pub fn to_string<T: Serialize>(objects: &[T]) -> YamlResult<String> {
+    let mut writer = YamlWriter::new(Vec::default());
+    writer.write_all(objects)?;
+    Ok(String::from_utf8(writer.finish()?).expect("serde_yaml writes UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{manifest::Manifest, recipes::*};
+
+    #[test]
+    fn round_trips_through_parser() -> anyhow::Result<()> {
+        let stack = Stack::builder("example", true, Environment::Ephemeral("pr-1".into()))
+            .with_resource(Resource::Nginx(Nginx::new("nginx:1.27".into(), 2)))
+            .build();
+
+        let mut yaml = Vec::default();
+        stack.write_yaml(&mut yaml)?;
+        let yaml = String::from_utf8(yaml)?;
+        assert!(yaml.starts_with(
+            "# Generated by flagship\n# Stack: example\n# Environment: ephemeral (pr-1)\n---\napiVersion: v1\nkind: Namespace\n"
+        ));
+        assert_eq!(yaml.matches("\n---\n").count(), 3);
+
+        let values = stack.as_k8s()?;
+        let parsed: Vec<_> = Manifest::parse_all(&yaml)?
+            .iter()
+            .map(Manifest::to_value)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(parsed, values);
+        assert_eq!(to_string(&values)?, yaml.split_once("---\n").unwrap().1);
+
+        Ok(())
+    }
+}